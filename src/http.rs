@@ -1,38 +1,128 @@
-use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
+pub mod body;
 pub mod client;
 mod codec;
+pub mod encoding;
 pub mod error;
+pub mod header;
 pub mod http1;
 #[cfg(feature = "http2")]
 pub mod http2;
+pub mod options;
 pub mod pool;
 pub mod pooled_conn;
 mod proto_conn;
+pub mod proxy;
 pub mod request;
 pub mod status;
 #[cfg(test)]
 pub(crate) mod test_utils;
+pub mod tls;
+pub mod transport;
 pub mod url;
 pub mod utf8_utils;
 
 use crate::http::error::ErrorKind;
 use crate::http::utf8_utils::UTF8Utils;
+pub use body::{ResponseBody, StreamingResponse};
+pub use encoding::{BodyCompression, Compression, ContentEncoding};
 pub use error::Error;
+pub use error::TimeoutKind;
+pub use header::HeaderMap;
+pub use options::{ConnOptions, Http2Settings, Timeouts};
+pub use proxy::{Proxy, ProxyKind};
+pub use tls::{PinMismatch, SpkiPin, TlsOptions};
+pub use transport::{Connector, Transport};
 
-type Result<T> = std::result::Result<T, Error>;
+pub(crate) type Result<T> = std::result::Result<T, Error>;
 type Success = Result<()>;
 pub type HttpClient = client::Client;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Method {
     GET,
+    HEAD,
     POST,
     PUT,
     PATCH,
     DELETE,
     CONNECT,
+    OPTIONS,
+    TRACE,
+    Extension(String),
+}
+
+impl Method {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::GET => "GET",
+            Self::HEAD => "HEAD",
+            Self::POST => "POST",
+            Self::PUT => "PUT",
+            Self::PATCH => "PATCH",
+            Self::DELETE => "DELETE",
+            Self::CONNECT => "CONNECT",
+            Self::OPTIONS => "OPTIONS",
+            Self::TRACE => "TRACE",
+            Self::Extension(name) => name,
+        }
+    }
+
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self.as_str(),
+            "GET" | "HEAD" | "PUT" | "DELETE" | "OPTIONS" | "TRACE"
+        )
+    }
+
+    fn normalize(self) -> Self {
+        match self {
+            Self::Extension(name) => Self::from_str(&name).unwrap_or(Self::Extension(name)),
+            method => method,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Success {
+        let valid = !self.as_str().is_empty()
+            && self
+                .as_str()
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+        match valid {
+            true => Ok(()),
+            false => Err(Error::user(&format!("invalid method {:?}", self.as_str()))),
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let method = match name {
+            "GET" => Self::GET,
+            "HEAD" => Self::HEAD,
+            "POST" => Self::POST,
+            "PUT" => Self::PUT,
+            "PATCH" => Self::PATCH,
+            "DELETE" => Self::DELETE,
+            "CONNECT" => Self::CONNECT,
+            "OPTIONS" => Self::OPTIONS,
+            "TRACE" => Self::TRACE,
+            name => Self::Extension(name.to_string()),
+        };
+        method.check()?;
+
+        Ok(method)
+    }
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
@@ -84,8 +174,9 @@ impl<'a> TryFrom<&[u8]> for Protocol {
 pub struct Response {
     pub protocol: Protocol,
     pub status_code: u16,
-    pub headers: HashMap<String, String>,
+    pub headers: HeaderMap,
     pub body: Vec<u8>,
+    pub compression: Option<Compression>,
 }
 
 impl Display for Response {
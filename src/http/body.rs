@@ -0,0 +1,212 @@
+use crate::http::encoding::{self, Compression, ContentEncoding};
+use crate::http::{Error, HeaderMap, Protocol, Response, Result};
+use std::fmt::{Debug, Formatter};
+use std::io::{self, Read};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+mod tests;
+
+pub const BODY_CHANNEL_CAPACITY: usize = 4;
+pub const UPLOAD_CHUNK_SIZE: usize = 16_384;
+
+pub type BodySender = SyncSender<Result<Vec<u8>>>;
+
+pub fn body_channel() -> (BodySender, ResponseBody) {
+    let (body_tx, body_rx) = sync_channel(BODY_CHANNEL_CAPACITY);
+
+    (body_tx, ResponseBody::new(body_rx))
+}
+
+#[derive(Clone)]
+pub struct BodyReader {
+    source: Arc<Mutex<Box<dyn Read + Send>>>,
+    length: Option<u64>,
+}
+
+impl Debug for BodyReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BodyReader")
+            .field("length", &self.length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BodyReader {
+    pub fn new<R: Read + Send + 'static>(source: R, length: Option<u64>) -> Self {
+        Self {
+            source: Arc::new(Mutex::new(Box::new(source))),
+            length,
+        }
+    }
+
+    pub fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    pub(crate) fn read_chunk(&self, max: usize) -> Result<Vec<u8>> {
+        let mut source = self
+            .source
+            .lock()
+            .map_err(|_| Error::client("request body reader poisoned"))?;
+        let mut chunk = Vec::with_capacity(max);
+        Read::take(&mut *source, max as u64).read_to_end(&mut chunk)?;
+
+        Ok(chunk)
+    }
+}
+
+#[derive(Debug, Default)]
+struct BodyState {
+    received: u64,
+    error: Option<Error>,
+}
+
+struct ChunkReader {
+    chunks: Receiver<Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+    state: Arc<Mutex<BodyState>>,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => {
+                    if let Ok(mut state) = self.state.lock() {
+                        state.received += chunk.len() as u64;
+                    }
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Ok(Err(e)) => {
+                    let message = e.to_string();
+                    if let Ok(mut state) = self.state.lock() {
+                        state.error = Some(e);
+                    }
+                    return Err(io::Error::other(message));
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let read = buf.len().min(self.chunk.len() - self.position);
+        buf[..read].copy_from_slice(&self.chunk[self.position..self.position + read]);
+        self.position += read;
+
+        Ok(read)
+    }
+}
+
+pub struct ResponseBody {
+    reader: Box<dyn Read + Send>,
+    state: Arc<Mutex<BodyState>>,
+}
+
+impl Debug for ResponseBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseBody")
+            .field("received", &self.received())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResponseBody {
+    fn new(chunks: Receiver<Result<Vec<u8>>>) -> Self {
+        let state = Arc::new(Mutex::new(BodyState::default()));
+        let reader = ChunkReader {
+            chunks,
+            chunk: Vec::new(),
+            position: 0,
+            state: state.clone(),
+        };
+
+        Self {
+            reader: Box::new(reader),
+            state,
+        }
+    }
+
+    pub(crate) fn decode(&mut self, encodings: &[ContentEncoding]) {
+        let reader = std::mem::replace(&mut self.reader, Box::new(io::empty()));
+        self.reader = encoding::lazy_decoder(encodings, reader);
+    }
+
+    pub fn received(&self) -> u64 {
+        self.state.lock().map_or(0, |state| state.received)
+    }
+
+    fn take_error(&self) -> Option<Error> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|mut state| state.error.take())
+    }
+}
+
+impl Read for ResponseBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+#[derive(Debug)]
+pub struct StreamingResponse {
+    pub protocol: Protocol,
+    pub status_code: u16,
+    pub headers: HeaderMap,
+    pub content_encodings: Vec<ContentEncoding>,
+    pub body: ResponseBody,
+}
+
+impl StreamingResponse {
+    pub(crate) fn new(
+        head: Response,
+        body: ResponseBody,
+        content_encodings: Vec<ContentEncoding>,
+    ) -> Self {
+        Self {
+            protocol: head.protocol,
+            status_code: head.status_code,
+            headers: head.headers,
+            content_encodings,
+            body,
+        }
+    }
+
+    pub fn compressed_size(&self) -> Option<u64> {
+        match self.content_encodings.is_empty() {
+            true => None,
+            false => Some(self.body.received()),
+        }
+    }
+
+    pub fn into_response(mut self) -> Result<Response> {
+        let mut body = Vec::new();
+        if let Err(e) = self.body.read_to_end(&mut body) {
+            return Err(self
+                .body
+                .take_error()
+                .unwrap_or_else(|| Error::server(&e.to_string())));
+        }
+        let compression = self.compressed_size().map(|compressed_size| Compression {
+            encodings: self.content_encodings,
+            compressed_size,
+        });
+
+        Ok(Response {
+            protocol: self.protocol,
+            status_code: self.status_code,
+            headers: self.headers,
+            body,
+            compression,
+        })
+    }
+}
+
+impl Read for StreamingResponse {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.body.read(buf)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::http::body::{body_channel, StreamingResponse};
+use crate::http::error::ErrorKind;
+use crate::http::{Error, Response};
+use std::io::Read;
+
+fn head() -> Response {
+    Response {
+        protocol: Default::default(),
+        status_code: 200,
+        headers: Default::default(),
+        body: Vec::new(),
+        compression: None,
+    }
+}
+
+#[test]
+fn reads_across_chunks() {
+    let (body_tx, body) = body_channel();
+    let mut response = StreamingResponse::new(head(), body, Vec::new());
+    body_tx.send(Ok(b"hel".to_vec())).unwrap();
+    body_tx.send(Ok(b"lo wor".to_vec())).unwrap();
+    body_tx.send(Ok(b"ld".to_vec())).unwrap();
+    drop(body_tx);
+    let mut buffer = [0; 4];
+    assert_eq!(response.read(&mut buffer).unwrap(), 3);
+    assert_eq!(&buffer[..3], b"hel");
+    assert_eq!(response.read(&mut buffer).unwrap(), 4);
+    assert_eq!(&buffer, b"lo w");
+    let mut rest = String::new();
+    response.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "orld");
+    assert_eq!(response.read(&mut buffer).unwrap(), 0);
+}
+
+#[test]
+fn errors_surface_to_the_reader() {
+    let (body_tx, body) = body_channel();
+    let response = StreamingResponse::new(head(), body, Vec::new());
+    body_tx.send(Ok(b"partial".to_vec())).unwrap();
+    body_tx
+        .send(Err(Error::server(
+            "connection closed before response was complete",
+        )))
+        .unwrap();
+    let error = response.into_response().unwrap_err();
+    assert!(matches!(error.kind, ErrorKind::Server));
+}
@@ -1,67 +1,279 @@
-use super::{pooled_conn::PooledConn, Response, Result};
+use super::{
+    pooled_conn::{AbortHandle, PooledConn},
+    Response, Result,
+};
+use crate::http::body::{body_channel, StreamingResponse};
+use crate::http::encoding::{self, BodyCompression};
+use crate::http::error::TimeoutKind;
+use crate::http::options::{ConnOptions, Http2Settings, Timeouts};
+use crate::http::proxy::Proxy;
 use crate::http::request::RequestBuilder;
+use crate::http::tls::TlsOptions;
+use crate::http::transport::Connector;
 use crate::http::url::Url;
-use crate::http::{Error, ErrorKind, Protocol};
+#[cfg(feature = "http2")]
+use crate::http::HeaderMap;
+use crate::http::{Error, ErrorKind, Method, Protocol, Success};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Instant;
 
 pub struct Client {
     pooled_conn: Option<PooledConn>,
+    options: ConnOptions,
 }
 impl Client {
     pub fn new() -> Self {
-        Self { pooled_conn: None }
+        Self::with_options(ConnOptions::default())
     }
 
-    pub fn execute(&mut self, request: RequestBuilder) -> Result<Response> {
-        let host = request.url.authority();
-        let connection = match &mut self.pooled_conn {
-            Some(conn) => conn,
-            None => self
-                .pooled_conn
-                .insert(PooledConn::new(&host, request.protocol)?),
-        };
-        if !connection.host.eq(&host) {
-            connection.join_thread()?;
-            *connection = PooledConn::new(&host, request.protocol)?;
+    pub fn with_options(options: ConnOptions) -> Self {
+        Self {
+            pooled_conn: None,
+            options,
         }
+    }
+
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        self.options.tls = tls;
+
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.options.timeouts = timeouts;
+
+        self
+    }
+
+    pub fn connector<C: Connector + 'static>(mut self, connector: C) -> Self {
+        self.options = self.options.connector(connector);
+
+        self
+    }
+
+    pub fn unix_socket<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.options.unix_socket = Some(path.into());
+
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.options.proxy = Some(proxy);
+
+        self
+    }
+
+    pub fn proxy_from_env(mut self) -> Self {
+        self.options.env_proxy = true;
+
+        self
+    }
+
+    pub fn compress_requests(mut self, compression: BodyCompression) -> Self {
+        self.options.request_compression = Some(compression);
+
+        self
+    }
+
+    pub fn on_informational<F: Fn(&Response) + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.options = self.options.on_informational(handler);
+
+        self
+    }
+
+    fn prepare(&self, request: &mut RequestBuilder) {
+        encoding::prepare(request);
+        if request.compression.is_none() {
+            request.compression = self.options.request_compression;
+        }
+    }
+
+    pub fn execute(&mut self, mut request: RequestBuilder) -> Result<Response> {
+        self.prepare(&mut request);
+        let connection = self.connection_for(&request)?;
         connection.send_request(request)?;
 
-        connection.check_response()
+        encoding::decode_response(connection.check_response()?)
     }
 
-    pub fn connect(&mut self, host: &Url) -> Result<()> {
-        let conn = match PooledConn::new(&host.authority(), Protocol::default()) {
-            Ok(c) => c,
-            Err(e) => {
-                if let ErrorKind::Protocol = e.kind {
-                    PooledConn::new(&host.authority(), Protocol::HTTP1)?
-                } else {
-                    return Err(e);
+    pub fn execute_batch(
+        &mut self,
+        mut requests: Vec<RequestBuilder>,
+    ) -> Result<Vec<Result<Response>>> {
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.prepare_batch(&mut requests)?;
+        let count = requests.len();
+        let connection = self.connection_for(&requests[0])?;
+        connection.send_batch(requests)?;
+        let mut responses = Vec::with_capacity(count);
+        for _ in 0..count {
+            responses.push(
+                connection
+                    .check_response()
+                    .and_then(encoding::decode_response),
+            );
+        }
+
+        Ok(responses)
+    }
+
+    pub fn send_concurrent(&mut self, mut requests: Vec<RequestBuilder>) -> Result<Completions> {
+        let (completion_tx, completion_rx) = channel();
+        let pending = (0..requests.len()).collect();
+        if requests.is_empty() {
+            return Ok(Completions {
+                completion_rx,
+                pending,
+                deadline: None,
+                abort: None,
+            });
+        }
+        self.prepare_batch(&mut requests)?;
+        let deadline = self
+            .options
+            .timeouts
+            .deadline
+            .map(|deadline| Instant::now() + deadline);
+        let connection = self.connection_for(&requests[0])?;
+        connection.send_multiplexed(requests, completion_tx)?;
+
+        Ok(Completions {
+            completion_rx,
+            pending,
+            deadline,
+            abort: Some(connection.abort_handle()),
+        })
+    }
+
+    fn prepare_batch(&mut self, requests: &mut [RequestBuilder]) -> Success {
+        for request in requests.iter() {
+            Url::reparse(&request.unparsed_url)?;
+        }
+        let host = requests[0].url.authority();
+        if requests
+            .iter()
+            .any(|request| request.url.authority() != host)
+        {
+            return Err(Error::user("batched requests must share a host"));
+        }
+        for request in requests.iter_mut() {
+            self.prepare(request);
+        }
+
+        Ok(())
+    }
+
+    pub fn pipeline(mut self, depth: usize) -> Self {
+        self.options.pipeline_depth = depth;
+
+        self
+    }
+
+    pub fn http2_settings(mut self, settings: Http2Settings) -> Self {
+        self.options = self.options.http2_settings(settings);
+
+        self
+    }
+
+    #[cfg(feature = "http2")]
+    pub fn on_push<F: Fn(&HeaderMap) -> bool + Send + Sync + 'static>(mut self, filter: F) -> Self {
+        self.options = self.options.on_push(filter);
+
+        self
+    }
+
+    pub fn cancel_push(&mut self, url: &str) -> Result<()> {
+        let url = Url::parse(url)?;
+        match &mut self.pooled_conn {
+            Some(connection) if connection.host == url.authority() => {
+                match connection.is_active() {
+                    true => connection.cancel_push(url),
+                    false => Ok(()),
                 }
             }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn send_streaming(&mut self, mut request: RequestBuilder) -> Result<StreamingResponse> {
+        self.prepare(&mut request);
+        let head_request = request.method == Method::HEAD;
+        let (body_tx, mut body) = body_channel();
+        let connection = self.connection_for(&request)?;
+        connection.send_streaming(request, body_tx)?;
+        let mut head = connection.check_response()?;
+        let content_encodings = match encoding::has_body(&head, head_request) {
+            true => encoding::content_encodings(&head).unwrap_or_default(),
+            false => Vec::new(),
         };
-        self.pooled_conn = Some(conn);
+        if !content_encodings.is_empty() {
+            encoding::strip_headers(&mut head);
+            body.decode(&content_encodings);
+        }
 
-        Ok(())
+        Ok(StreamingResponse::new(head, body, content_encodings))
+    }
+
+    fn connection_for(&mut self, request: &RequestBuilder) -> Result<&mut PooledConn> {
+        Url::reparse(&request.unparsed_url)?;
+        let host = request.url.authority();
+        let reopen = match &mut self.pooled_conn {
+            Some(connection) if !connection.host.eq(&host) => {
+                if !connection.is_streaming() {
+                    connection.join_thread()?;
+                }
+                true
+            }
+            Some(connection) => !connection.is_active(),
+            None => true,
+        };
+        match reopen {
+            true => Ok(self.pooled_conn.insert(Self::open(
+                &request.url,
+                request.protocol,
+                &self.options,
+            )?)),
+            false => self
+                .pooled_conn
+                .as_mut()
+                .ok_or_else(|| Error::client("no open connection")),
+        }
+    }
+
+    pub fn connect(&mut self, host: &Url) -> Result<()> {
+        self.connect_proto(host, Protocol::default())
     }
 
     pub fn connect_proto(&mut self, host: &Url, protocol: Protocol) -> Result<()> {
-        let conn = match PooledConn::new(&host.authority(), protocol) {
-            Ok(c) => c,
+        self.pooled_conn = Some(Self::open(host, protocol, &self.options)?);
+
+        Ok(())
+    }
+
+    fn open(host: &Url, protocol: Protocol, options: &ConnOptions) -> Result<PooledConn> {
+        match PooledConn::new(host, protocol, options) {
+            Ok(c) => Ok(c),
             Err(e) => {
                 if let ErrorKind::Protocol = e.kind {
-                    PooledConn::new(&host.authority(), Protocol::HTTP1)?
+                    PooledConn::new(host, Protocol::HTTP1, options)
                 } else {
-                    return Err(e);
+                    Err(e)
                 }
             }
-        };
-        self.pooled_conn = Some(conn);
-
-        Ok(())
+        }
     }
 
     pub fn reset_connection(&mut self) -> Result<()> {
         if let Some(pooled) = &mut self.pooled_conn {
+            if pooled.is_streaming() {
+                return Err(Error::user("connection is still streaming a response body"));
+            }
             if let Some(connection) = pooled.join_thread()? {
                 pooled.spawn_connection(connection);
                 return Ok(());
@@ -93,3 +305,48 @@ impl Client {
         }
     }
 }
+
+pub struct Completions {
+    completion_rx: Receiver<(usize, Result<Response>)>,
+    pending: Vec<usize>,
+    deadline: Option<Instant>,
+    abort: Option<AbortHandle>,
+}
+
+impl Iterator for Completions {
+    type Item = (usize, Result<Response>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let received = match self.deadline {
+            Some(deadline) => self
+                .completion_rx
+                .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => self.completion_rx.recv().map_err(RecvTimeoutError::from),
+        };
+        match received {
+            Ok((index, response)) => {
+                self.pending.retain(|pending| *pending != index);
+
+                Some((index, response.and_then(encoding::decode_response)))
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(abort) = self.abort.take() {
+                    abort.abort();
+                }
+                let index = self.pending.remove(0);
+
+                Some((
+                    index,
+                    Err(Error::timeout(
+                        "request deadline elapsed",
+                        TimeoutKind::Deadline,
+                    )),
+                ))
+            }
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
@@ -1,17 +1,53 @@
 use super::{Response, Result};
+use crate::http::body::BodyReader;
+use crate::http::request::RequestBuilder;
+use crate::http::transport::Transport;
+use crate::http::url::Url;
 use crate::http::utf8_utils::UTF8Utils;
 use crate::http::{Error, Protocol, Success};
-use crate::rest::response::RequestBuilder;
-use rustls::ClientConnection as TlsClient;
-use rustls::StreamOwned as TlsStream;
-use std::net::TcpStream;
 use std::str::FromStr;
 
 pub trait Codec: Send {
     fn encode_request(&mut self, request: RequestBuilder) -> Result<Vec<u8>>;
-    fn decode_response(&mut self, conn: &mut TlsStream<TlsClient, TcpStream>) -> Result<Response>;
+    fn write_body(&mut self, body: BodyReader, conn: &mut dyn Transport) -> Success;
+    fn decode_head(&mut self, conn: &mut dyn Transport) -> Result<Response>;
+    fn decode_chunk(&mut self, conn: &mut dyn Transport) -> Result<Option<Vec<u8>>>;
+    fn decode_body(&mut self, head: Response, conn: &mut dyn Transport) -> Result<Response> {
+        let mut response = head;
+        while let Some(chunk) = self.decode_chunk(conn)? {
+            response.body.extend(chunk);
+        }
+
+        Ok(response)
+    }
+    fn multiplex(
+        &mut self,
+        requests: Vec<RequestBuilder>,
+        _conn: &mut dyn Transport,
+        complete: &mut dyn FnMut(usize, Result<Response>),
+    ) -> Success {
+        for index in 0..requests.len() {
+            complete(index, Err(Error::protocol("multiplexing requires http2")));
+        }
+
+        Ok(())
+    }
+    fn take_pushed(
+        &mut self,
+        _request: &RequestBuilder,
+        _conn: &mut dyn Transport,
+    ) -> Result<Option<Response>> {
+        Ok(None)
+    }
+    fn cancel_push(&mut self, _url: &Url, _conn: &mut dyn Transport) -> Success {
+        Ok(())
+    }
+    fn closes_connection(&self) -> bool {
+        false
+    }
+    fn response_started(&self) -> bool;
     fn empty_buffer(&self) -> Vec<u8>;
-    fn prelude(&mut self, conn: &mut TlsStream<TlsClient, TcpStream>) -> Success;
+    fn prelude(&mut self, conn: &mut dyn Transport) -> Success;
     fn decode_status(&self, status: &[u8]) -> Result<u16> {
         u16::from_str(&status.as_utf8_lossy())
             .map_err(|_e| Error::server("could not parse status code"))
@@ -0,0 +1,300 @@
+use crate::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::http::request::RequestBuilder;
+use crate::http::{Error, Response, Result, Success};
+#[cfg(any(feature = "gzip", feature = "deflate", feature = "brotli"))]
+use std::io::Write;
+use std::io::{self, Cursor, Read};
+
+#[cfg(test)]
+mod tests;
+
+pub const GZIP: &[u8] = b"gzip";
+pub const X_GZIP: &[u8] = b"x-gzip";
+pub const DEFLATE: &[u8] = b"deflate";
+pub const BROTLI: &[u8] = b"br";
+pub const ZSTD: &[u8] = b"zstd";
+pub const IDENTITY: &[u8] = b"identity";
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Compression {
+    pub encodings: Vec<ContentEncoding>,
+    pub compressed_size: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BodyCompression {
+    pub encoding: ContentEncoding,
+    pub threshold: usize,
+}
+
+impl BodyCompression {
+    pub fn new(encoding: ContentEncoding) -> Self {
+        Self {
+            encoding,
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+        }
+    }
+
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+
+        self
+    }
+}
+
+impl ContentEncoding {
+    pub const ALL: [Self; 4] = [Self::Gzip, Self::Deflate, Self::Brotli, Self::Zstd];
+
+    pub fn from_bytes(value: &[u8]) -> Option<Self> {
+        match value.trim_ascii().to_ascii_lowercase().as_slice() {
+            GZIP | X_GZIP => Some(Self::Gzip),
+            DEFLATE => Some(Self::Deflate),
+            BROTLI => Some(Self::Brotli),
+            ZSTD => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            Self::Gzip => GZIP,
+            Self::Deflate => DEFLATE,
+            Self::Brotli => BROTLI,
+            Self::Zstd => ZSTD,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Self::Gzip => cfg!(feature = "gzip"),
+            Self::Deflate => cfg!(feature = "deflate"),
+            Self::Brotli => cfg!(feature = "brotli"),
+            Self::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    pub fn enabled() -> Vec<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|encoding| encoding.is_enabled())
+            .collect()
+    }
+
+    pub fn decoder(&self, source: Box<dyn Read + Send>) -> Result<Box<dyn Read + Send>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(source))),
+            #[cfg(feature = "deflate")]
+            Self::Deflate => Ok(Box::new(flate2::read::ZlibDecoder::new(source))),
+            #[cfg(feature = "brotli")]
+            Self::Brotli => Ok(Box::new(brotli::Decompressor::new(source, 4096))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(Box::new(zstd::Decoder::new(source)?)),
+            #[cfg(not(all(
+                feature = "gzip",
+                feature = "deflate",
+                feature = "brotli",
+                feature = "zstd"
+            )))]
+            _ => {
+                drop(source);
+                Err(Error::user(&format!(
+                    "{} decoding is not enabled",
+                    String::from_utf8_lossy(self.as_bytes())
+                )))
+            }
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(
+                    Vec::with_capacity(data.len() / 2),
+                    flate2::Compression::default(),
+                );
+                encoder.write_all(data)?;
+
+                Ok(encoder.finish()?)
+            }
+            #[cfg(feature = "deflate")]
+            Self::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(
+                    Vec::with_capacity(data.len() / 2),
+                    flate2::Compression::default(),
+                );
+                encoder.write_all(data)?;
+
+                Ok(encoder.finish()?)
+            }
+            #[cfg(feature = "brotli")]
+            Self::Brotli => {
+                let mut encoded = Vec::with_capacity(data.len() / 2);
+                brotli::CompressorWriter::new(&mut encoded, 4096, 5, 22).write_all(data)?;
+
+                Ok(encoded)
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(zstd::encode_all(data, 0)?),
+            #[cfg(not(all(
+                feature = "gzip",
+                feature = "deflate",
+                feature = "brotli",
+                feature = "zstd"
+            )))]
+            _ => {
+                let _ = data;
+                Err(Error::user(&format!(
+                    "{} encoding is not enabled",
+                    String::from_utf8_lossy(self.as_bytes())
+                )))
+            }
+        }
+    }
+}
+
+pub fn accept_encoding() -> Option<Vec<u8>> {
+    let enabled = ContentEncoding::enabled();
+    match enabled.is_empty() {
+        true => None,
+        false => Some(
+            enabled
+                .iter()
+                .map(|encoding| encoding.as_bytes())
+                .collect::<Vec<&[u8]>>()
+                .join(&b", "[..]),
+        ),
+    }
+}
+
+pub fn prepare(request: &mut RequestBuilder) {
+    if request.headers.contains(ACCEPT_ENCODING) {
+        return;
+    }
+    if let Some(accept) = accept_encoding() {
+        request.headers.insert(ACCEPT_ENCODING, accept);
+    }
+}
+
+pub fn compress_body(request: &mut RequestBuilder) -> Success {
+    let compression = match request.compression {
+        Some(compression) => compression,
+        None => return Ok(()),
+    };
+    let body = match request.body.as_ref() {
+        Some(body) if body.len() >= compression.threshold => body,
+        _ => return Ok(()),
+    };
+    if request.headers.contains(CONTENT_ENCODING) {
+        return Ok(());
+    }
+    request.body = Some(compression.encoding.encode(body)?);
+    request.headers.remove(CONTENT_LENGTH);
+    request
+        .headers
+        .insert(CONTENT_ENCODING, compression.encoding.as_bytes());
+
+    Ok(())
+}
+
+pub fn content_encodings(response: &Response) -> Option<Vec<ContentEncoding>> {
+    let mut encodings = Vec::new();
+    for value in response.headers.get_all(CONTENT_ENCODING) {
+        for coding in value.split(|b| *b == b',') {
+            let coding = coding.trim_ascii();
+            if coding.is_empty() || coding.eq_ignore_ascii_case(IDENTITY) {
+                continue;
+            }
+            match ContentEncoding::from_bytes(coding) {
+                Some(encoding) if encoding.is_enabled() => encodings.push(encoding),
+                _ => return None,
+            }
+        }
+    }
+    match encodings.is_empty() {
+        true => None,
+        false => Some(encodings),
+    }
+}
+
+pub fn decoder(
+    encodings: &[ContentEncoding],
+    mut source: Box<dyn Read + Send>,
+) -> Result<Box<dyn Read + Send>> {
+    for encoding in encodings.iter().rev() {
+        source = encoding.decoder(source)?;
+    }
+
+    Ok(source)
+}
+
+struct LazyDecoder {
+    encodings: Vec<ContentEncoding>,
+    source: Option<Box<dyn Read + Send>>,
+    decoder: Option<Box<dyn Read + Send>>,
+}
+
+impl Read for LazyDecoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(source) = self.source.take() {
+            let decoder =
+                decoder(&self.encodings, source).map_err(|e| io::Error::other(e.to_string()))?;
+            self.decoder = Some(decoder);
+        }
+        match self.decoder.as_mut() {
+            Some(decoder) => decoder.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+pub fn lazy_decoder(
+    encodings: &[ContentEncoding],
+    source: Box<dyn Read + Send>,
+) -> Box<dyn Read + Send> {
+    Box::new(LazyDecoder {
+        encodings: encodings.to_vec(),
+        source: Some(source),
+        decoder: None,
+    })
+}
+
+pub fn strip_headers(response: &mut Response) {
+    response.headers.remove(CONTENT_ENCODING);
+    response.headers.remove(CONTENT_LENGTH);
+}
+
+pub fn has_body(head: &Response, head_request: bool) -> bool {
+    !head_request
+        && !matches!(head.status_code, 204 | 304)
+        && head.headers.content_length() != Some(0)
+}
+
+pub fn decode_response(mut response: Response) -> Result<Response> {
+    let encodings = match content_encodings(&response) {
+        Some(encodings) if !response.body.is_empty() => encodings,
+        _ => return Ok(response),
+    };
+    strip_headers(&mut response);
+    let compressed = std::mem::take(&mut response.body);
+    let compressed_size = compressed.len() as u64;
+    decoder(&encodings, Box::new(Cursor::new(compressed)))?
+        .read_to_end(&mut response.body)
+        .map_err(|e| Error::server(&format!("could not decode response body: {e}")))?;
+    response.compression = Some(Compression {
+        encodings,
+        compressed_size,
+    });
+
+    Ok(response)
+}
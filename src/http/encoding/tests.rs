@@ -0,0 +1,208 @@
+use crate::http::encoding::{self, ContentEncoding};
+use crate::http::request::RequestBuilder;
+use crate::http::{HeaderMap, Response};
+
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+const JSON: &[u8] = br#"{"users":[{"id":1,"name":"ada"},{"id":2,"name":"grace"}]}"#;
+
+fn response(encoding: &str, body: Vec<u8>) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.append("Content-Encoding", encoding);
+    headers.append("Content-Length", body.len().to_string());
+
+    Response {
+        protocol: Default::default(),
+        status_code: 200,
+        headers,
+        body,
+        compression: None,
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn accept_encoding_lists_enabled_codings() {
+    let mut request = RequestBuilder::get("http://example.test/");
+    encoding::prepare(&mut request);
+    let expected: Vec<&[u8]> = ContentEncoding::enabled()
+        .iter()
+        .map(|encoding| encoding.as_bytes())
+        .collect();
+    assert_eq!(
+        request.headers.get("accept-encoding").map(<[u8]>::to_vec),
+        encoding::accept_encoding()
+    );
+    assert_eq!(
+        encoding::accept_encoding().unwrap_or_default(),
+        expected.join(&b", "[..])
+    );
+    let mut request =
+        RequestBuilder::get("http://example.test/").header((b"Accept-Encoding", b"identity"));
+    encoding::prepare(&mut request);
+    assert_eq!(request.headers.get_str("accept-encoding"), Some("identity"));
+}
+
+#[test]
+fn unknown_encoding_is_left_untouched() {
+    let decoded = encoding::decode_response(response("compress", b"raw".to_vec())).unwrap();
+    assert_eq!(decoded.body, b"raw");
+    assert!(decoded.compression.is_none());
+    assert_eq!(
+        decoded.headers.get_str("content-encoding"),
+        Some("compress")
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_body_is_decoded() {
+    let compressed = gzip(JSON);
+    let size = compressed.len() as u64;
+    let decoded = encoding::decode_response(response("gzip", compressed)).unwrap();
+    assert_eq!(decoded.body, JSON);
+    let compression = decoded.compression.unwrap();
+    assert_eq!(compression.encodings, vec![ContentEncoding::Gzip]);
+    assert_eq!(compression.compressed_size, size);
+    assert!(!decoded.headers.contains("content-encoding"));
+    assert!(!decoded.headers.contains("content-length"));
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn bodiless_response_keeps_encoding_headers() {
+    let mut head = response("gzip", Vec::new());
+    head.headers.insert("Content-Length", "1234");
+    let decoded = encoding::decode_response(head).unwrap();
+    assert!(decoded.compression.is_none());
+    assert_eq!(decoded.headers.get_str("content-encoding"), Some("gzip"));
+    assert_eq!(decoded.headers.content_length(), Some(1234));
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn corrupt_body_is_an_error() {
+    assert!(encoding::decode_response(response("gzip", b"not gzip".to_vec())).is_err());
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn deflate_body_is_decoded() {
+    use std::io::Write;
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(JSON).unwrap();
+    let decoded =
+        encoding::decode_response(response("deflate", encoder.finish().unwrap())).unwrap();
+    assert_eq!(decoded.body, JSON);
+}
+
+#[cfg(feature = "brotli")]
+#[test]
+fn brotli_body_is_decoded() {
+    use std::io::Write;
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+        encoder.write_all(JSON).unwrap();
+    }
+    let decoded = encoding::decode_response(response("br", compressed)).unwrap();
+    assert_eq!(decoded.body, JSON);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_body_is_decoded() {
+    let compressed = zstd::encode_all(JSON, 3).unwrap();
+    let decoded = encoding::decode_response(response("zstd", compressed)).unwrap();
+    assert_eq!(decoded.body, JSON);
+}
+
+#[cfg(all(feature = "gzip", feature = "brotli"))]
+#[test]
+fn stacked_encodings_are_decoded_in_reverse() {
+    use std::io::Write;
+    let mut compressed = Vec::new();
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+        encoder.write_all(&gzip(JSON)).unwrap();
+    }
+    let decoded = encoding::decode_response(response("gzip, br", compressed)).unwrap();
+    assert_eq!(decoded.body, JSON);
+    assert_eq!(
+        decoded.compression.unwrap().encodings,
+        vec![ContentEncoding::Gzip, ContentEncoding::Brotli]
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn streaming_body_is_decoded() {
+    use crate::http::body::{body_channel, StreamingResponse};
+    use std::thread;
+    let compressed = gzip(JSON);
+    let (body_tx, mut body) = body_channel();
+    body.decode(&[ContentEncoding::Gzip]);
+    let head = response("gzip", Vec::new());
+    let response = StreamingResponse::new(head, body, vec![ContentEncoding::Gzip]);
+    let chunks: Vec<Vec<u8>> = compressed.chunks(7).map(<[u8]>::to_vec).collect();
+    thread::spawn(move || {
+        for chunk in chunks {
+            body_tx.send(Ok(chunk)).unwrap();
+        }
+    });
+    let decoded = response.into_response().unwrap();
+    assert_eq!(decoded.body, JSON);
+    assert_eq!(
+        decoded.compression.unwrap().compressed_size,
+        compressed.len() as u64
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn request_body_compressed_above_threshold() {
+    use crate::http::encoding::BodyCompression;
+    use std::io::Read;
+    let compression = BodyCompression::new(ContentEncoding::Gzip).threshold(32);
+    let mut small = RequestBuilder::post("http://example.test/")
+        .body(b"tiny")
+        .compress(compression);
+    encoding::compress_body(&mut small).unwrap();
+    assert_eq!(small.body.as_deref(), Some(&b"tiny"[..]));
+    assert!(!small.headers.contains("content-encoding"));
+
+    let mut large = RequestBuilder::post("http://example.test/")
+        .header((b"Content-Length", JSON.len().to_string().as_bytes()))
+        .body(JSON)
+        .compress(compression);
+    encoding::compress_body(&mut large).unwrap();
+    assert_eq!(large.headers.get_str("content-encoding"), Some("gzip"));
+    assert!(!large.headers.contains("content-length"));
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(large.body.unwrap().as_slice())
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, JSON);
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn disabled_encoding_is_an_error() {
+    use crate::http::encoding::BodyCompression;
+    let mut request = RequestBuilder::post("http://example.test/")
+        .body(b"payload")
+        .compress(BodyCompression::new(ContentEncoding::Zstd).threshold(0));
+    assert!(encoding::compress_body(&mut request).is_err());
+}
@@ -38,6 +38,19 @@ impl Error {
         Self::new(message, ErrorKind::Thread(source))
     }
 
+    pub fn timeout(message: &str, kind: TimeoutKind) -> Self {
+        Self::new(message, ErrorKind::Timeout(kind, None))
+    }
+
+    pub(crate) fn io(e: std::io::Error, timeout: TimeoutKind) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                Self::new(timeout.message(), ErrorKind::Timeout(timeout, e.some_box()))
+            }
+            _ => Self::new(&e.to_string(), ErrorKind::Connection(e.some_box())),
+        }
+    }
+
     pub fn protocol(message: &str) -> Self {
         Self::new(message, ErrorKind::Protocol)
     }
@@ -64,10 +77,42 @@ pub enum ErrorKind {
     Server,
     Connection(Option<Box<dyn Any + Send>>),
     Protocol,
+    Timeout(TimeoutKind, Option<Box<dyn Any + Send>>),
     #[cfg(feature = "http2")]
     Http2Protocol(ErrorCode),
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TimeoutKind {
+    Connect,
+    Read,
+    Write,
+    Deadline,
+    Idle,
+}
+
+impl TimeoutKind {
+    fn message(&self) -> &'static str {
+        match self {
+            Self::Connect => "connect timed out",
+            Self::Read => "read timed out",
+            Self::Write => "write timed out",
+            Self::Deadline => "request deadline elapsed",
+            Self::Idle => "connection idle timeout elapsed",
+        }
+    }
+}
+
+pub(crate) trait IoTimeout<T> {
+    fn timeout_as(self, kind: TimeoutKind) -> Result<T, Error>;
+}
+
+impl<T> IoTimeout<T> for std::io::Result<T> {
+    fn timeout_as(self, kind: TimeoutKind) -> Result<T, Error> {
+        self.map_err(|e| Error::io(e, kind))
+    }
+}
+
 pub trait SomeError {
     fn some_box(self) -> Option<Box<dyn Any + Send>>;
 }
@@ -80,7 +125,7 @@ impl<T: 'static + Sized + Send> SomeError for T {
 
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
-        Self::new(&e.to_string(), ErrorKind::Connection(e.some_box()))
+        Self::io(e, TimeoutKind::Read)
     }
 }
 
@@ -0,0 +1,254 @@
+use crate::http::utf8_utils::UTF8Utils;
+use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+mod tests;
+
+pub const HOST: &[u8] = b"Host";
+pub const AUTHORIZATION: &[u8] = b"Authorization";
+pub const PROXY_AUTHORIZATION: &[u8] = b"Proxy-Authorization";
+pub const CONTENT_LENGTH: &[u8] = b"Content-Length";
+pub const CONTENT_TYPE: &[u8] = b"Content-Type";
+pub const CONTENT_ENCODING: &[u8] = b"Content-Encoding";
+pub const TRANSFER_ENCODING: &[u8] = b"Transfer-Encoding";
+pub const USER_AGENT: &[u8] = b"User-Agent";
+pub const ACCEPT: &[u8] = b"Accept";
+pub const ACCEPT_CHARSET: &[u8] = b"Accept-Charset";
+pub const ACCEPT_ENCODING: &[u8] = b"Accept-Encoding";
+pub const ACCEPT_LANGUAGE: &[u8] = b"Accept-Language";
+pub const CONNECTION: &[u8] = b"Connection";
+pub const KEEP_ALIVE: &[u8] = b"Keep-Alive";
+pub const EXPECT: &[u8] = b"Expect";
+pub const MAX_FORWARDS: &[u8] = b"Max-Forwards";
+pub const FROM: &[u8] = b"From";
+pub const REFERER: &[u8] = b"Referer";
+pub const DATE: &[u8] = b"Date";
+pub const EXPIRES: &[u8] = b"Expires";
+pub const LAST_MODIFIED: &[u8] = b"Last-Modified";
+pub const LOCATION: &[u8] = b"Location";
+pub const LINK: &[u8] = b"Link";
+pub const SET_COOKIE: &[u8] = b"Set-Cookie";
+pub const WWW_AUTHENTICATE: &[u8] = b"WWW-Authenticate";
+pub mod values {
+    pub const ALL: &[u8] = b"*/*";
+    pub const JSON: &[u8] = b"application/json";
+    pub const OCTET_STREAM: &[u8] = b"application/octet-stream";
+    pub const UTF8: &[u8] = b"charset=utf-8";
+    pub const TEXT_HTML: &[u8] = b"text/html";
+    pub const TEXT_PLAIN: &[u8] = b"text/plain";
+    pub const EN_US: &[u8] = b"en_US";
+    pub const KEEP_ALIVE: &[u8] = b"keep-alive";
+    pub const CLOSE: &[u8] = b"close";
+    pub const CHUNKED: &[u8] = b"chunked";
+    pub const CONTINUE: &[u8] = b"100-continue";
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const SECONDS_PER_DAY: u64 = 86_400;
+
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct HeaderMap {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Debug for HeaderMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.entries
+                    .iter()
+                    .map(|(key, value)| (key.as_utf8_lossy(), value.as_utf8_lossy())),
+            )
+            .finish()
+    }
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains<K: AsRef<[u8]>>(&self, name: K) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn get<K: AsRef<[u8]>>(&self, name: K) -> Option<&[u8]> {
+        self.get_all(name).next()
+    }
+
+    pub fn get_str<K: AsRef<[u8]>>(&self, name: K) -> Option<&str> {
+        self.get(name)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    pub fn get_all<K: AsRef<[u8]>>(&self, name: K) -> impl Iterator<Item = &[u8]> {
+        let name = name.as_ref().to_vec();
+        self.entries
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(&name))
+            .map(|(_, value)| value.as_slice())
+    }
+
+    pub fn insert<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, name: K, value: V) {
+        self.remove(name.as_ref());
+        self.append(name, value);
+    }
+
+    pub fn append<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, name: K, value: V) {
+        self.entries
+            .push((name.as_ref().to_vec(), value.as_ref().to_vec()));
+    }
+
+    pub fn remove<K: AsRef<[u8]>>(&mut self, name: K) -> bool {
+        let len = self.entries.len();
+        self.entries
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name.as_ref()));
+
+        len != self.entries.len()
+    }
+
+    pub fn retain<F: FnMut(&[u8], &[u8]) -> bool>(&mut self, mut keep: F) {
+        self.entries.retain(|(key, value)| keep(key, value));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.get_str(CONTENT_LENGTH)
+            .and_then(|length| u64::from_str(length.trim()).ok())
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.get_str(CONTENT_TYPE)
+    }
+
+    pub fn date(&self) -> Option<SystemTime> {
+        self.get_str(DATE).and_then(parse_http_date)
+    }
+
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.get_str(EXPIRES).and_then(parse_http_date)
+    }
+
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.get_str(LAST_MODIFIED).and_then(parse_http_date)
+    }
+
+    pub fn connection_close(&self) -> bool {
+        self.connection_option(values::CLOSE)
+    }
+
+    pub fn connection_keep_alive(&self) -> bool {
+        self.connection_option(values::KEEP_ALIVE)
+    }
+
+    fn connection_option(&self, option: &[u8]) -> bool {
+        self.get_all(CONNECTION)
+            .flat_map(|value| value.split(|b| *b == b','))
+            .any(|token| token.trim_ascii().eq_ignore_ascii_case(option))
+    }
+
+    pub fn keep_alive_timeout(&self) -> Option<Duration> {
+        self.get_str(KEEP_ALIVE)?
+            .split(',')
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("timeout"))
+            .and_then(|(_, seconds)| u64::from_str(seconds.trim()).ok())
+            .map(Duration::from_secs)
+    }
+}
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> Extend<(K, V)> for HeaderMap {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.append(key, value);
+        }
+    }
+}
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut headers = Self::new();
+        headers.extend(iter);
+
+        headers
+    }
+}
+
+impl IntoIterator for HeaderMap {
+    type Item = (Vec<u8>, Vec<u8>);
+    type IntoIter = std::vec::IntoIter<(Vec<u8>, Vec<u8>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let (day, month, year, time) = match parts.as_slice() {
+        [_, day, month, year, time, "GMT"] => (*day, *month, *year, *time),
+        [_, date, time, "GMT"] => {
+            let mut date = date.split('-');
+            (date.next()?, date.next()?, date.next()?, *time)
+        }
+        [_, month, day, time, year] => (*day, *month, *year, *time),
+        _ => return None,
+    };
+    let day = u64::from_str(day).ok()?;
+    let month = MONTHS
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(month))? as u64
+        + 1;
+    let year = match (year.len(), u64::from_str(year).ok()?) {
+        (2, year) if year < 70 => 2000 + year,
+        (2, year) => 1900 + year,
+        (_, year) => year,
+    };
+    let mut time = time.split(':').map(|part| u64::from_str(part).ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if year < 1970 || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    let seconds = days_since_epoch(year, month, day) * SECONDS_PER_DAY
+        + hours * 3600
+        + minutes * 60
+        + seconds;
+
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    let year = match month <= 2 {
+        true => year - 1,
+        false => year,
+    };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,82 @@
+use crate::http::header::{parse_http_date, HeaderMap, CONTENT_LENGTH, SET_COOKIE};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn case_insensitive_lookup() {
+    let mut headers = HeaderMap::new();
+    headers.append("content-length", "42");
+    assert_eq!(headers.get(CONTENT_LENGTH), Some(&b"42"[..]));
+    assert_eq!(headers.get_str("CONTENT-LENGTH"), Some("42"));
+    assert_eq!(headers.content_length(), Some(42));
+    assert!(headers.contains("Content-length"));
+    assert!(!headers.contains("Content-Type"));
+}
+
+#[test]
+fn repeated_headers_keep_order() {
+    let mut headers = HeaderMap::new();
+    headers.append(SET_COOKIE, "a=1");
+    headers.append("Link", "</style.css>; rel=preload");
+    headers.append("set-cookie", "b=2");
+    let cookies: Vec<&[u8]> = headers.get_all("Set-Cookie").collect();
+    assert_eq!(cookies, vec![&b"a=1"[..], &b"b=2"[..]]);
+    assert_eq!(headers.len(), 3);
+    headers.insert("SET-COOKIE", "c=3");
+    let cookies: Vec<&[u8]> = headers.get_all(SET_COOKIE).collect();
+    assert_eq!(cookies, vec![&b"c=3"[..]]);
+    assert!(headers.remove("link"));
+    assert!(!headers.remove("link"));
+    assert_eq!(headers.len(), 1);
+}
+
+#[test]
+fn typed_accessors() {
+    let headers: HeaderMap = vec![
+        ("Content-Type", "application/json; charset=utf-8"),
+        ("Content-Length", "not a number"),
+        ("Date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(
+        headers.content_type(),
+        Some("application/json; charset=utf-8")
+    );
+    assert_eq!(headers.content_length(), None);
+    assert_eq!(
+        headers.date(),
+        Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+    );
+    assert_eq!(headers.last_modified(), None);
+}
+
+#[test]
+fn http_date_formats() {
+    let expected = Some(UNIX_EPOCH + Duration::from_secs(784_111_777));
+    assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+    assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+    assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), expected);
+    assert_eq!(
+        parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+        Some(UNIX_EPOCH + Duration::from_secs(1_709_164_800))
+    );
+    assert_eq!(parse_http_date("yesterday"), None);
+    assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+}
+
+#[test]
+fn connection_directives() {
+    let headers: HeaderMap = vec![
+        ("Connection", "Upgrade, Close"),
+        ("Keep-Alive", "max=100, timeout = 7"),
+    ]
+    .into_iter()
+    .collect();
+    assert!(headers.connection_close());
+    assert_eq!(headers.keep_alive_timeout(), Some(Duration::from_secs(7)));
+    let headers: HeaderMap = vec![("Connection", "keep-alive"), ("Keep-Alive", "max=5")]
+        .into_iter()
+        .collect();
+    assert!(!headers.connection_close());
+    assert_eq!(headers.keep_alive_timeout(), None);
+}
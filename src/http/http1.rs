@@ -1,3 +1,4 @@
 pub(crate) mod codec;
+pub(crate) mod parser;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,354 @@
+use crate::http::header::{values::CHUNKED, CONTENT_LENGTH, TRANSFER_ENCODING};
+use crate::http::status;
+use crate::http::utf8_utils::{UTF8Utils, COLON, CR, CRLF, LF, SP};
+use crate::http::{Error, HeaderMap, Protocol, Response, Result};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+#[cfg(test)]
+mod tests;
+
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+const CHUNK_EXTENSION: u8 = b';';
+const HTTP10: &[u8] = b"HTTP/1.0";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum State {
+    StatusLine,
+    Headers,
+    Body(usize),
+    ChunkSize,
+    ChunkData(usize),
+    ChunkEnd,
+    Trailers,
+    UntilClose,
+    Done,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum BodyChunk {
+    Data(Vec<u8>),
+    Pending,
+    End,
+}
+
+#[derive(Debug)]
+pub struct ResponseParser {
+    buffer: Vec<u8>,
+    state: State,
+    response: Option<Response>,
+    body: Vec<u8>,
+    head_requests: VecDeque<bool>,
+    http10: bool,
+    closes: bool,
+}
+
+impl Default for ResponseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseParser {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            state: State::StatusLine,
+            response: None,
+            body: Vec::new(),
+            head_requests: VecDeque::new(),
+            http10: false,
+            closes: false,
+        }
+    }
+
+    pub fn head_request(&mut self, head_request: bool) {
+        self.head_requests.push_back(head_request);
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn parse(&mut self) -> Result<Option<Response>> {
+        self.advance(false)?;
+        match self.state {
+            State::Done => Ok(self.complete()),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn parse_head(&mut self) -> Result<Option<Response>> {
+        self.advance(true)?;
+        match self.state {
+            State::StatusLine | State::Headers => Ok(None),
+            _ => Ok(self.response.clone()),
+        }
+    }
+
+    pub fn parse_body(&mut self) -> Result<BodyChunk> {
+        self.advance(false)?;
+        if !self.body.is_empty() {
+            return Ok(BodyChunk::Data(std::mem::take(&mut self.body)));
+        }
+        match self.state {
+            State::Done => {
+                self.complete();
+                Ok(BodyChunk::End)
+            }
+            _ => Ok(BodyChunk::Pending),
+        }
+    }
+
+    pub fn finish(&mut self) -> Result<Response> {
+        if let Some(response) = self.parse()? {
+            return Ok(response);
+        }
+        match self.state {
+            State::UntilClose => self
+                .complete()
+                .ok_or_else(|| Error::connection("no server response", None)),
+            _ => Err(self.closed_error()),
+        }
+    }
+
+    pub fn finish_body(&mut self) -> Result<BodyChunk> {
+        match self.parse_body()? {
+            BodyChunk::Pending if self.state == State::UntilClose => {
+                self.complete();
+                Ok(BodyChunk::End)
+            }
+            BodyChunk::Pending => Err(self.closed_error()),
+            chunk => Ok(chunk),
+        }
+    }
+
+    pub fn closes_connection(&self) -> bool {
+        self.closes
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.state == State::StatusLine && self.buffer.is_empty()
+    }
+
+    pub fn closed_error(&self) -> Error {
+        match self.is_idle() {
+            true => Error::connection("no server response", None),
+            false => Error::server("connection closed before response was complete"),
+        }
+    }
+
+    fn advance(&mut self, head_only: bool) -> Result<()> {
+        loop {
+            let progressed = match self.state {
+                State::StatusLine => self.parse_status_line()?,
+                State::Headers => self.parse_header_line()?,
+                _ if head_only => false,
+                State::Body(remaining) => self.take_body(remaining, State::Body, State::Done),
+                State::ChunkSize => self.parse_chunk_size()?,
+                State::ChunkData(remaining) => {
+                    self.take_body(remaining, State::ChunkData, State::ChunkEnd)
+                }
+                State::ChunkEnd => self.parse_chunk_end()?,
+                State::Trailers => self.parse_trailer_line()?,
+                State::UntilClose => {
+                    self.drain_body(self.buffer.len());
+                    false
+                }
+                State::Done => false,
+            };
+            if !progressed {
+                return Ok(());
+            }
+        }
+    }
+
+    fn complete(&mut self) -> Option<Response> {
+        self.state = State::StatusLine;
+        let body = std::mem::take(&mut self.body);
+
+        self.response
+            .take()
+            .map(|response| Response { body, ..response })
+    }
+
+    fn take_line(&mut self) -> Result<Option<Vec<u8>>> {
+        match self.buffer.windows(2).position(|w| w == CRLF) {
+            Some(end) => {
+                let line = self.buffer[..end].to_vec();
+                self.buffer.drain(..end + 2);
+
+                Ok(Some(line))
+            }
+            None if self.buffer.len() > MAX_HEAD_SIZE => {
+                Err(Error::server("response head too large"))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn parse_status_line(&mut self) -> Result<bool> {
+        let line = match self.take_line()? {
+            Some(line) => line,
+            None => return Ok(false),
+        };
+        let mut parts = line.splitn(3, |b| *b == SP);
+        let version = parts.next().unwrap_or_default();
+        self.http10 = version == HTTP10;
+        let protocol = match version {
+            HTTP10 => Protocol::HTTP1,
+            version => version.try_into()?,
+        };
+        let status_code = parts
+            .next()
+            .and_then(|code| u16::from_str(&code.as_utf8_lossy()).ok())
+            .ok_or_else(|| Error::server("could not parse status code"))?;
+        self.response = Some(Response {
+            protocol,
+            status_code,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+            compression: None,
+        });
+        self.state = State::Headers;
+
+        Ok(true)
+    }
+
+    fn parse_header_line(&mut self) -> Result<bool> {
+        let line = match self.take_line()? {
+            Some(line) => line,
+            None => return Ok(false),
+        };
+        match line.is_empty() {
+            true => {
+                self.state = self.body_state()?;
+                let keep_alive = self
+                    .response
+                    .as_ref()
+                    .is_some_and(|response| response.headers.connection_keep_alive());
+                self.closes = self.state == State::UntilClose || (self.http10 && !keep_alive);
+            }
+            false => self.insert_header(&line)?,
+        }
+
+        Ok(true)
+    }
+
+    fn parse_trailer_line(&mut self) -> Result<bool> {
+        let line = match self.take_line()? {
+            Some(line) => line,
+            None => return Ok(false),
+        };
+        match line.is_empty() {
+            true => self.state = State::Done,
+            false => self.insert_header(&line)?,
+        }
+
+        Ok(true)
+    }
+
+    fn insert_header(&mut self, line: &[u8]) -> Result<()> {
+        let colon = line
+            .iter()
+            .position(|b| *b == COLON)
+            .ok_or_else(|| Error::server("could not parse header"))?;
+        if let Some(response) = self.response.as_mut() {
+            response
+                .headers
+                .append(line[..colon].trim_ascii(), line[colon + 1..].trim_ascii());
+        }
+
+        Ok(())
+    }
+
+    fn header(&self, name: &[u8]) -> Option<&str> {
+        self.response
+            .as_ref()
+            .and_then(|response| response.headers.get_str(name))
+    }
+
+    fn body_state(&mut self) -> Result<State> {
+        let status_code = self.response.as_ref().map_or(0, |r| r.status_code);
+        if status::is_informational(status_code) {
+            return Ok(State::Done);
+        }
+        let head_request = self.head_requests.pop_front().unwrap_or(false);
+        if head_request || status_code < 200 || status_code == 204 || status_code == 304 {
+            return Ok(State::Done);
+        }
+        if let Some(encoding) = self.header(TRANSFER_ENCODING) {
+            let chunked = encoding
+                .rsplit(',')
+                .next()
+                .is_some_and(|coding| coding.trim().as_bytes().eq_ignore_ascii_case(CHUNKED));
+            return Ok(match chunked {
+                true => State::ChunkSize,
+                false => State::UntilClose,
+            });
+        }
+        match self.header(CONTENT_LENGTH) {
+            Some(length) => match usize::from_str(length.trim()) {
+                Ok(0) => Ok(State::Done),
+                Ok(length) => Ok(State::Body(length)),
+                Err(_) => Err(Error::server("invalid content length")),
+            },
+            None => Ok(State::UntilClose),
+        }
+    }
+
+    fn take_body(&mut self, remaining: usize, partial: fn(usize) -> State, next: State) -> bool {
+        if self.buffer.is_empty() {
+            return false;
+        }
+        let taken = self.drain_body(remaining);
+        self.state = match remaining - taken {
+            0 => next,
+            left => partial(left),
+        };
+
+        true
+    }
+
+    fn drain_body(&mut self, len: usize) -> usize {
+        let len = len.min(self.buffer.len());
+        self.body.extend(self.buffer.drain(..len));
+
+        len
+    }
+
+    fn parse_chunk_size(&mut self) -> Result<bool> {
+        let line = match self.take_line()? {
+            Some(line) => line,
+            None => return Ok(false),
+        };
+        let size = line
+            .split(|b| *b == CHUNK_EXTENSION)
+            .next()
+            .unwrap_or_default()
+            .as_utf8_lossy()
+            .trim()
+            .to_string();
+        let size = usize::from_str_radix(&size, 16)
+            .map_err(|_| Error::server("invalid chunk encoding"))?;
+        self.state = match size {
+            0 => State::Trailers,
+            size => State::ChunkData(size),
+        };
+
+        Ok(true)
+    }
+
+    fn parse_chunk_end(&mut self) -> Result<bool> {
+        match self.buffer.as_slice() {
+            [] | [CR] => Ok(false),
+            [CR, LF, ..] => {
+                self.buffer.drain(..2);
+                self.state = State::ChunkSize;
+
+                Ok(true)
+            }
+            _ => Err(Error::server("invalid chunk encoding")),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::http::http1::parser::ResponseParser;
+use crate::http::Response;
+
+fn feed_bytewise(parser: &mut ResponseParser, bytes: &[u8]) -> Option<Response> {
+    let mut response = None;
+    for byte in bytes {
+        assert!(response.is_none(), "response completed before input ended");
+        parser.feed(&[*byte]);
+        response = parser.parse().unwrap();
+    }
+
+    response
+}
+
+#[test]
+fn headers_split_across_reads() {
+    let mut parser = ResponseParser::new();
+    let response = feed_bytewise(
+        &mut parser,
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nSet-Cookie: a=1\r\n\
+          set-cookie:b=2\r\ncontent-length: 5\r\n\r\nhello",
+    )
+    .unwrap();
+    assert_eq!(response.status_code, 200);
+    let cookies: Vec<&[u8]> = response.headers.get_all("Set-Cookie").collect();
+    assert_eq!(cookies, vec![&b"a=1"[..], &b"b=2"[..]]);
+    assert_eq!(response.headers.content_type(), Some("text/plain"));
+    assert_eq!(response.body, b"hello");
+}
+
+#[test]
+fn chunked_with_extensions_and_trailers() {
+    let mut parser = ResponseParser::new();
+    let response = feed_bytewise(
+        &mut parser,
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\n\r\n\
+          4;name=value\r\nWiki\r\n\
+          6\r\npedia \r\n\
+          E\r\nin \r\n\r\nchunks.\r\n\
+          0\r\nExpires: never\r\n\r\n",
+    )
+    .unwrap();
+    assert_eq!(response.body, b"Wikipedia in \r\n\r\nchunks.");
+    assert_eq!(response.headers.get_str("Expires"), Some("never"));
+}
+
+#[test]
+fn chunk_terminator_inside_body() {
+    let mut parser = ResponseParser::new();
+    parser.feed(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n7\r\n\r\n0\r\n\r\n\r\n0\r\n\r\n",
+    );
+    let response = parser.parse().unwrap().unwrap();
+    assert_eq!(response.body, b"\r\n0\r\n\r\n");
+}
+
+#[test]
+fn binary_body_is_untouched() {
+    let body: Vec<u8> = (0..=255).chain(0..=255).collect();
+    let mut message =
+        format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+    message.extend(&body);
+    let mut parser = ResponseParser::new();
+    parser.feed(&message);
+    assert_eq!(parser.parse().unwrap().unwrap().body, body);
+}
+
+#[test]
+fn body_delimited_by_close() {
+    let mut parser = ResponseParser::new();
+    parser.feed(b"HTTP/1.0 200 OK\r\nServer: legacy\r\n\r\nfirst ");
+    assert!(parser.parse().unwrap().is_none());
+    parser.feed(b"second\0");
+    assert!(parser.parse().unwrap().is_none());
+    let response = parser.finish().unwrap();
+    assert_eq!(response.body, b"first second\0");
+}
+
+#[test]
+fn pipelined_responses_keep_leftover_bytes() {
+    let mut parser = ResponseParser::new();
+    parser.feed(b"HTTP/1.1 204 No Content\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+    assert_eq!(parser.parse().unwrap().unwrap().status_code, 204);
+    let response = parser.parse().unwrap().unwrap();
+    assert_eq!(response.body, b"ok");
+    assert!(parser.parse().unwrap().is_none());
+}
+
+#[test]
+fn truncated_response_is_an_error() {
+    let mut parser = ResponseParser::new();
+    assert!(parser.finish().is_err());
+    parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort");
+    assert!(parser.parse().unwrap().is_none());
+    assert!(parser.finish().is_err());
+}
+
+#[test]
+fn malformed_framing() {
+    let mut parser = ResponseParser::new();
+    parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: ten\r\n\r\n");
+    assert!(parser.parse().is_err());
+    let mut parser = ResponseParser::new();
+    parser.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n");
+    assert!(parser.parse().is_err());
+    let mut parser = ResponseParser::new();
+    parser.feed(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nokXX");
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn bodiless_responses_ignore_content_length() {
+    let mut parser = ResponseParser::new();
+    parser.head_request(true);
+    parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\n\r\nHTTP/1.1 304 Not Modified\r\n");
+    let response = parser.parse().unwrap().unwrap();
+    assert_eq!(response.headers.content_length(), Some(1234));
+    assert!(response.body.is_empty());
+    parser.head_request(false);
+    parser.feed(b"Content-Length: 10\r\n\r\n");
+    let response = parser.parse().unwrap().unwrap();
+    assert_eq!(response.status_code, 304);
+    assert!(response.body.is_empty());
+}
@@ -1,8 +1,8 @@
 use crate::http::request::headers::values::{ALL, KEEP_ALIVE};
 use crate::http::request::headers::{ACCEPT, CONNECTION};
-use crate::http::request::RequestBuilder;
+use crate::http::request::{ArrayFormat, RequestBuilder};
 use crate::http::test_utils::*;
-use crate::http::Response;
+use crate::http::{Protocol, Response};
 use crate::HttpClient;
 
 #[test]
@@ -47,3 +47,145 @@ fn query_test() {
         None,
     );
 }
+
+#[test]
+fn plain_http_custom_port() {
+    let (port, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    let mut client = HttpClient::new();
+    let request =
+        RequestBuilder::get(&format!("http://127.0.0.1:{port}/ping")).protocol(Protocol::HTTP1);
+    let response = client.execute(request).unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body, b"hello");
+    let request = server.join().unwrap();
+    assert!(request.starts_with(b"GET /ping HTTP/1.1\r\n"));
+    let host = format!("Host: 127.0.0.1:{port}\r\n");
+    assert!(request.windows(host.len()).any(|w| w == host.as_bytes()));
+}
+
+#[test]
+fn query_builder_on_the_wire() {
+    let (port, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    let mut client = HttpClient::new();
+    let request = RequestBuilder::get(&format!("http://127.0.0.1:{port}/items?sort=asc"))
+        .protocol(Protocol::HTTP1)
+        .query(vec![("name", "jane doe")])
+        .query_array("ids", &["1", "2"], ArrayFormat::Repeat)
+        .query_array("tags", &["a/b"], ArrayFormat::Brackets);
+    client.execute(request).unwrap();
+    let request = server.join().unwrap();
+    assert!(request.starts_with(
+        b"GET /items?sort=asc&name=jane%20doe&ids=1&ids=2&tags%5B%5D=a%2Fb HTTP/1.1\r\n"
+    ));
+}
+
+#[test]
+fn close_delimited_binary_body() {
+    let (port, server) = serve_once(b"HTTP/1.0 200 OK\r\n\r\n\0\x01\r\n0\r\n\r\n\0");
+    let mut client = HttpClient::new();
+    let request =
+        RequestBuilder::get(&format!("http://127.0.0.1:{port}/raw")).protocol(Protocol::HTTP1);
+    let response = client.execute(request).unwrap();
+    server.join().unwrap();
+    assert_eq!(response.body, b"\0\x01\r\n0\r\n\r\n\0");
+}
+
+#[cfg(unix)]
+fn serve_unix(
+    name: &str,
+    response: &'static [u8],
+) -> (std::path::PathBuf, std::thread::JoinHandle<Vec<u8>>) {
+    use std::io::Write;
+    let path = std::env::temp_dir().join(format!("envoy-{}-{name}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let request = read_request_head(&mut stream);
+        stream.write_all(response).unwrap();
+
+        request
+    });
+
+    (path, server)
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_socket_option() {
+    let (path, server) = serve_unix("option", b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}");
+    let mut client = HttpClient::new().unix_socket(&path);
+    let request = RequestBuilder::get("http://docker/v1.41/info").protocol(Protocol::HTTP1);
+    let response = client.execute(request).unwrap();
+    assert_eq!(response.body, b"{}");
+    let request = server.join().unwrap();
+    assert!(request.starts_with(b"GET /v1.41/info HTTP/1.1\r\nHost: docker\r\n"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_socket_url() {
+    let (path, server) = serve_unix(
+        "url",
+        b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n",
+    );
+    let encoded = path.to_string_lossy().replace('/', "%2F");
+    let mut client = HttpClient::new();
+    let request = RequestBuilder::get(&format!("unix://{encoded}/_ping")).protocol(Protocol::HTTP1);
+    let response = client.execute(request).unwrap();
+    assert_eq!(response.status_code, 204);
+    let request = server.join().unwrap();
+    assert!(request.starts_with(b"GET /_ping HTTP/1.1\r\n"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn head_response_keeps_encoding_headers() {
+    let reply = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 1234\r\n\r\n";
+    let mut client = HttpClient::new();
+    let (port, _server) = serve_once(reply);
+    let request =
+        RequestBuilder::head(&format!("http://127.0.0.1:{port}/")).protocol(Protocol::HTTP1);
+    let response = client.execute(request).unwrap();
+    assert!(response.compression.is_none());
+    assert_eq!(response.headers.get_str("Content-Encoding"), Some("gzip"));
+    assert_eq!(response.headers.content_length(), Some(1234));
+    let (port, _server) = serve_once(reply);
+    let request =
+        RequestBuilder::head(&format!("http://127.0.0.1:{port}/")).protocol(Protocol::HTTP1);
+    let response = HttpClient::new().send_streaming(request).unwrap();
+    assert!(response.content_encodings.is_empty());
+    assert_eq!(response.headers.get_str("Content-Encoding"), Some("gzip"));
+    assert_eq!(response.headers.content_length(), Some(1234));
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_response_is_decoded() {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"compressed payload").unwrap();
+    let compressed = encoder.finish().unwrap();
+    let mut reply = format!(
+        "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+        compressed.len()
+    )
+    .into_bytes();
+    reply.extend(&compressed);
+    let (port, server) = serve_once(&reply);
+    let mut client = HttpClient::new();
+    let response = client
+        .execute(
+            RequestBuilder::get(&format!("http://127.0.0.1:{port}/")).protocol(Protocol::HTTP1),
+        )
+        .unwrap();
+    assert_eq!(response.body, b"compressed payload");
+    assert_eq!(
+        response.compression.unwrap().compressed_size,
+        compressed.len() as u64
+    );
+    let request = String::from_utf8(server.join().unwrap()).unwrap();
+    assert!(request.contains("Accept-Encoding: gzip"));
+}
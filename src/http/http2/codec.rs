@@ -1,20 +1,31 @@
+use crate::http::body::BodyReader;
 use crate::http::codec::Codec;
-use crate::http::error::SomeError;
+use crate::http::encoding;
+use crate::http::error::{IoTimeout, SomeError, TimeoutKind};
+use crate::http::http2::request::headers::{AUTHORITY, METHOD, PATH, SCHEME, STATUS};
 use crate::http::http2::request::Request;
 use crate::http::http2::stream::{State, Stream};
+use crate::http::options::{Http2Settings, PushFilter, DEFAULT_WINDOW_SIZE, MAX_WINDOW_SIZE};
 use crate::http::request::RequestBuilder;
+use crate::http::status;
+use crate::http::url::Url;
 use crate::http::utf8_utils::UTF8Utils;
+use crate::http::Method;
 use crate::http::Protocol::HTTP2;
-use crate::http::{proto_conn::H2, Error, Protocol, Response, Result, Success};
+use crate::http::{
+    proto_conn::H2, transport::Transport, Error, ErrorKind, HeaderMap, Protocol, Response, Result,
+    Success,
+};
 use hpack::{Decoder, Encoder};
-use rustls::{ClientConnection, StreamOwned};
-use std::collections::HashMap;
-use std::io::{Read, Write};
-use std::net::TcpStream;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 pub mod frames;
+#[cfg(test)]
+mod tests;
 use crate::http::http2::codec::frames::go_away::GoAway;
 use crate::http::http2::codec::frames::headers::Headers;
 use crate::http::http2::codec::frames::ping::Ping;
+use crate::http::http2::codec::frames::rst_stream::RstStream;
 use crate::http::http2::codec::frames::settings::{Identifier, Setting};
 use crate::http::http2::codec::frames::window_update::WindowUpdate;
 use frames::*;
@@ -26,113 +37,226 @@ pub struct Http2Codec<'a> {
     pub settings: StreamSettings,
     pub server_window_size: u32,
     pub client_window_size: u32,
+    local: Http2Settings,
+    read_timeout: Option<Duration>,
+    settings_sent: Option<Instant>,
+    push_filter: Option<PushFilter>,
+    promised: HashMap<u32, String>,
+    last_promised: u32,
+    pushed: HashMap<String, (Instant, Response)>,
+    streams: HashMap<u32, Stream>,
+    current: u32,
+    header_block: Option<HeaderBlock>,
+    unreleased: u32,
 }
 
+struct HeaderBlock {
+    stream_id: u32,
+    blocks: Vec<u8>,
+    end_stream: bool,
+    promised: Option<u32>,
+}
+
+type Exchanges = HashMap<u32, (usize, Option<Response>)>;
+
+const RELEASE_THRESHOLD: u32 = 16_384;
+const MAX_PUSHED: usize = 32;
+const MAX_PUSHED_BYTES: usize = 1 << 20;
+const PUSHED_MAX_AGE: Duration = Duration::from_secs(30);
+
 impl<'a> Codec for Http2Codec<'a> {
-    fn encode_request(&mut self, request: RequestBuilder) -> Result<Vec<u8>> {
+    fn encode_request(&mut self, mut request: RequestBuilder) -> Result<Vec<u8>> {
         if request.url.host.eq(b"ping") {
+            self.open_stream();
             return Ok(self.encode_ping());
         }
+        request.method.check()?;
+        let streaming = request.body_reader.is_some();
+        encoding::compress_body(&mut request)?;
+        self.open_stream();
         let request = Request::from(request);
-        let mut encoded = self.encode_header_frame(&request.raw_headers, request.data.is_some());
+        let mut encoded =
+            self.encode_header_frame(&request.raw_headers, request.data.is_some() || streaming);
         if let Some(data) = request.data {
-            let data_frame = DataFrame::parse_from_payload(
-                FrameHeader::new(FrameKind::Data, END_STREAM, self.last_stream),
-                &data,
-            )?;
-            encoded.extend(data_frame.encode());
+            self.current_stream()?.outgoing = Some(data);
+            encoded.extend(self.drain_outgoing(self.current)?);
         }
         self.last_stream += 2;
-        self.server_window_size -= encoded.len() as u32 - 9;
 
         Ok(encoded)
     }
 
-    fn decode_response(
-        &mut self,
-        conn: &mut StreamOwned<ClientConnection, TcpStream>,
-    ) -> Result<Response> {
-        let mut stream = Stream::new(self.last_stream);
-        while !stream.is_closed() {
-            let frame_header = self.expect_frame_header(conn)?;
-            if frame_header.is_malformed() {
-                self.send_go_away(conn)?;
-            }
-            if frame_header.length >= self.client_window_size {
-                self.update_window(conn)?;
-            }
-            self.client_window_size -= frame_header.length;
-            match frame_header.kind {
-                FrameKind::Headers => {
-                    let headers: HeadersFrame = self.expect_payload(conn, frame_header)?;
-                    if headers.payload.is_malformed() {
-                        self.send_go_away(conn)?;
-                    }
-                    if headers.is_stream_end() {
-                        stream.state = State::Closed;
-                    }
-                    stream.response_headers.extend(headers.payload.blocks);
-                }
-                FrameKind::Data => {
-                    let data: DataFrame = self.expect_payload(conn, frame_header)?;
-                    if data.payload.is_malformed() {
-                        self.send_go_away(conn)?;
-                    }
-                    if data.is_stream_end() {
-                        stream.state = State::Closed;
-                    }
-                    stream.response_data.extend(data.payload.blocks);
-                }
-                FrameKind::Continuation => {
-                    let continuation: ContinuationFrame =
-                        self.expect_payload(conn, frame_header)?;
-                    stream.response_headers.extend(continuation.payload.blocks);
-                }
-                FrameKind::Setting => self.update_settings(conn, frame_header)?,
-                FrameKind::WindowUpdate => self.handle_window_update(conn, frame_header)?,
-                FrameKind::RstStream => self.handle_stream_reset(conn, frame_header)?,
-                FrameKind::GoAway => self.handle_go_away(conn, frame_header)?,
-                FrameKind::Ping => return self.receive_ping(conn, frame_header),
-                FrameKind::PushPromise => {
-                    let _: PushPromiseFrame = self.expect_payload(conn, frame_header)?;
-                    if !self.settings.enable_push {
-                        self.send_go_away(conn)?;
-                    }
-                }
-                FrameKind::Priority => {
-                    let _: PriorityFrame = self.expect_payload(conn, frame_header)?;
+    fn write_body(&mut self, body: BodyReader, conn: &mut dyn Transport) -> Success {
+        let mut sent = 0;
+        loop {
+            let chunk = match body.length() == Some(sent) {
+                true => Vec::new(),
+                false => {
+                    let available = self.send_capacity(conn)?;
+                    let size = (self.settings.max_frame_size as i64).min(available);
+                    body.read_chunk(size as usize)?
                 }
-                FrameKind::Altsvc => {}
-                FrameKind::Origin => {}
+            };
+            if chunk.is_empty() && body.length().is_some_and(|length| length != sent) {
+                self.reset_stream(conn, self.current, ErrorCode::Cancel)?;
+                return Err(Error::user("request body ended before its declared length"));
+            }
+            sent += chunk.len() as u64;
+            let end_stream = chunk.is_empty() || body.length() == Some(sent);
+            let flags = match end_stream {
+                true => END_STREAM,
+                false => 0,
+            };
+            let frame = DataFrame::parse_from_payload(
+                FrameHeader::new(FrameKind::Data, flags, self.current),
+                &chunk,
+            )?;
+            conn.write_all(&frame.encode())
+                .timeout_as(TimeoutKind::Write)?;
+            self.consume_send_window(self.current, chunk.len() as u32);
+            if end_stream {
+                return Ok(());
+            }
+        }
+    }
+
+    fn decode_head(&mut self, conn: &mut dyn Transport) -> Result<Response> {
+        loop {
+            let stream = self.current_stream()?;
+            let (head, reset, closed) =
+                (stream.heads.pop_front(), stream.reset, stream.is_closed());
+            match (head, reset) {
+                (Some(headers), _) => return self.head_response(headers),
+                (None, Some(code)) => return Err(Self::reset_error(code)),
+                (None, None) if closed => return Err(Error::server("malformed response")),
+                (None, None) => {}
+            }
+            if let Some(pong) = self.decode_frame(conn)? {
+                self.current_stream()?.state = State::Closed;
+                return Ok(pong);
             }
         }
+    }
 
-        self.decode_stream(stream)
+    fn decode_chunk(&mut self, conn: &mut dyn Transport) -> Result<Option<Vec<u8>>> {
+        let id = self.current;
+        self.release_capacity(conn, id)?;
+        loop {
+            let stream = self.current_stream()?;
+            if !stream.response_data.is_empty() {
+                return Ok(Some(std::mem::take(&mut stream.response_data)));
+            }
+            if let Some(code) = stream.reset {
+                self.streams.remove(&id);
+                return Err(Self::reset_error(code));
+            }
+            if stream.is_closed() {
+                self.streams.remove(&id);
+                return Ok(None);
+            }
+            self.decode_frame(conn)?;
+        }
+    }
+
+    fn multiplex(
+        &mut self,
+        requests: Vec<RequestBuilder>,
+        conn: &mut dyn Transport,
+        complete: &mut dyn FnMut(usize, Result<Response>),
+    ) -> Success {
+        let mut pending: VecDeque<(usize, RequestBuilder)> =
+            requests.into_iter().enumerate().collect();
+        let mut open = Exchanges::new();
+        let result = self.drive(&mut pending, &mut open, conn, complete);
+        if let Err(e) = &result {
+            let indices = open.into_values().map(|(index, _)| index);
+            for index in indices.chain(pending.into_iter().map(|(index, _)| index)) {
+                complete(index, Err(Error::connection(&e.message, None)));
+            }
+        }
+
+        result
+    }
+
+    fn take_pushed(
+        &mut self,
+        request: &RequestBuilder,
+        conn: &mut dyn Transport,
+    ) -> Result<Option<Response>> {
+        if request.method != Method::GET {
+            return Ok(None);
+        }
+        let key = Self::push_key(&request.url);
+        while self.promised.values().any(|promised| *promised == key) {
+            self.decode_frame(conn)?;
+        }
+
+        Ok(self
+            .pushed
+            .remove(&key)
+            .filter(|(received, _)| received.elapsed() < PUSHED_MAX_AGE)
+            .map(|(_, response)| response))
+    }
+
+    fn cancel_push(&mut self, url: &Url, conn: &mut dyn Transport) -> Success {
+        let key = Self::push_key(url);
+        self.pushed.remove(&key);
+        let promised: Vec<u32> = self
+            .promised
+            .iter()
+            .filter(|(_, promised)| **promised == key)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in promised {
+            self.promised.remove(&id);
+            self.reset_stream(conn, id, ErrorCode::Cancel)?;
+            self.streams.remove(&id);
+        }
+
+        Ok(())
+    }
+
+    fn response_started(&self) -> bool {
+        self.streams
+            .get(&self.current)
+            .is_some_and(|stream| stream.response_started)
     }
 
     fn empty_buffer(&self) -> Vec<u8> {
         vec![0; 8192]
     }
 
-    fn prelude(&mut self, conn: &mut StreamOwned<ClientConnection, TcpStream>) -> Success {
+    fn prelude(&mut self, conn: &mut dyn Transport) -> Success {
         let mut handshake = PREFACE.to_vec();
-        handshake.extend(SettingsFrame::empty());
-        conn.write_all(&handshake)?;
-        conn.flush()?;
-        match conn.conn.alpn_protocol() {
-            Some(protocol) => {
-                if *protocol != *H2 {
-                    return Err(Error::protocol("http2 protocol rejected"));
+        handshake.extend(
+            SettingsFrame::new(
+                FrameHeader::new(FrameKind::Setting, 0, 0),
+                self.local_settings(),
+            )
+            .encode(),
+        );
+        conn.write_all(&handshake).timeout_as(TimeoutKind::Write)?;
+        conn.flush().timeout_as(TimeoutKind::Write)?;
+        self.settings_sent = Some(Instant::now());
+        let frame: SettingsFrame = match conn.is_secure() {
+            true => {
+                match conn.alpn_protocol() {
+                    Some(protocol) => {
+                        if *protocol != *H2 {
+                            return Err(Error::protocol("http2 protocol rejected"));
+                        }
+                    }
+                    None => return Err(Error::protocol("alpn protocol not set")),
                 }
+                self.expect_frame(conn)?
             }
-            None => return Err(Error::protocol("alpn protocol not set")),
-        }
-        let frame: SettingsFrame = self.expect_frame(conn)?;
+            false => self
+                .expect_frame(conn)
+                .map_err(|_| Error::protocol("h2c prior knowledge rejected"))?,
+        };
         self.settings.update(frame.payload);
         Self::ack_settings(conn)?;
-        self.update_window(conn)?;
-
-        Ok(())
+        self.open_connection_window(conn)
     }
 
     fn kind(&self) -> Protocol {
@@ -141,14 +265,31 @@ impl<'a> Codec for Http2Codec<'a> {
 }
 
 impl<'a> Http2Codec<'a> {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(
+        local: Http2Settings,
+        push_filter: Option<PushFilter>,
+        read_timeout: Option<Duration>,
+    ) -> Self {
+        let mut decoder = Decoder::new();
+        decoder.set_max_table_size(local.header_table_size as usize);
         Self {
             encoder: Encoder::new(),
-            decoder: Decoder::new(),
+            decoder,
             last_stream: 1,
             settings: StreamSettings::default(),
-            server_window_size: 65535,
-            client_window_size: 65535,
+            server_window_size: DEFAULT_WINDOW_SIZE,
+            client_window_size: DEFAULT_WINDOW_SIZE,
+            local,
+            read_timeout,
+            settings_sent: None,
+            push_filter,
+            promised: HashMap::new(),
+            last_promised: 0,
+            pushed: HashMap::new(),
+            streams: HashMap::new(),
+            current: 1,
+            header_block: None,
+            unreleased: 0,
         }
     }
 
@@ -158,12 +299,18 @@ impl<'a> Http2Codec<'a> {
         has_data: bool,
     ) -> Vec<u8> {
         let encoded = self.compress_headers(headers);
-        let flags = match has_data {
-            false => headers::Flags::EndHeaders as u8 | END_STREAM,
+        let mut fragments = encoded.chunks(self.settings.max_frame_size as usize);
+        let first = fragments.next().unwrap_or_default();
+        let end_headers = match encoded.len() <= first.len() {
             true => headers::Flags::EndHeaders as u8,
+            false => 0,
+        };
+        let flags = match has_data {
+            false => end_headers | END_STREAM,
+            true => end_headers,
         };
         let frame_header = FrameHeader {
-            length: encoded.len() as u32,
+            length: first.len() as u32,
             kind: FrameKind::Headers,
             flags,
             stream_identifier: self.last_stream,
@@ -173,11 +320,28 @@ impl<'a> Http2Codec<'a> {
             is_exclusive: None,
             stream_dependency: None,
             weight: None,
-            blocks: encoded,
+            blocks: first.to_vec(),
             padding: None,
         };
+        let mut frames = HeadersFrame::new(frame_header, headers).encode();
+        let mut fragments = fragments.peekable();
+        while let Some(fragment) = fragments.next() {
+            let flags = match fragments.peek() {
+                Some(_) => 0,
+                None => continuation::Flags::EndHeaders as u8,
+            };
+            frames.extend(
+                ContinuationFrame::new(
+                    FrameHeader::new(FrameKind::Continuation, flags, self.last_stream),
+                    continuation::Continuation {
+                        blocks: fragment.to_vec(),
+                    },
+                )
+                .encode(),
+            );
+        }
 
-        HeadersFrame::new(frame_header, headers).encode()
+        frames
     }
 
     pub fn compress_headers(&mut self, headers: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
@@ -189,31 +353,27 @@ impl<'a> Http2Codec<'a> {
         )
     }
 
-    pub fn decompress_headers(&mut self, headers: &[u8]) -> Result<HashMap<String, String>> {
+    pub fn decompress_headers(&mut self, headers: &[u8]) -> Result<HeaderMap> {
         Ok(self
             .decoder
             .decode(headers)
             .map_err(|_| Error::server("could not decompress headers"))?
             .into_iter()
-            .map(|(k, v)| (k.as_utf8_lossy().to_string(), v.as_utf8_lossy().to_string()))
-            .collect::<HashMap<String, String>>())
+            .collect())
     }
 
-    pub fn expect_frame_header(
-        &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
-    ) -> Result<FrameHeader> {
+    pub fn expect_frame_header(&mut self, stream: &mut dyn Transport) -> Result<FrameHeader> {
         let mut buffer = [0; 9];
         stream
             .read_exact(&mut buffer)
-            .map_err(|e| Error::connection(&e.to_string(), e.some_box()))?;
+            .timeout_as(TimeoutKind::Read)?;
 
-        Ok(FrameHeader::from(&buffer))
+        FrameHeader::try_from(buffer.as_slice())
     }
 
     pub fn expect_payload<P: FramePayload>(
         &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
+        stream: &mut dyn Transport,
         frame_header: FrameHeader,
     ) -> Result<Frame<P>> {
         let payload = self.try_read_buf(stream, frame_header.length)?;
@@ -223,72 +383,691 @@ impl<'a> Http2Codec<'a> {
 
     pub fn expect_frame<P: FramePayload>(
         &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
+        stream: &mut dyn Transport,
     ) -> Result<Frame<P>> {
         let frame_header = self.expect_frame_header(stream)?;
 
         self.expect_payload(stream, frame_header)
     }
 
-    pub fn decode_stream(&mut self, stream: Stream) -> Result<Response> {
-        let headers = self.decompress_headers(&stream.response_headers)?;
+    fn decode_frame(&mut self, conn: &mut dyn Transport) -> Result<Option<Response>> {
+        let frame_header = self.next_frame_header(conn)?;
+        if frame_header.is_malformed() {
+            self.send_go_away(conn)?;
+        }
+        if frame_header.length > self.local.max_frame_size {
+            Self::write_go_away(conn, ErrorCode::FrameSizeError)?;
+            return Err(Error::http2(
+                "frame exceeds maximum frame size",
+                ErrorCode::FrameSizeError,
+            ));
+        }
+        match frame_header.kind {
+            FrameKind::Headers => {
+                let headers: HeadersFrame = self.expect_payload(conn, frame_header)?;
+                if headers.payload.is_malformed() {
+                    self.send_go_away(conn)?;
+                }
+                let end_headers = headers.is_end_headers();
+                self.header_block = Some(HeaderBlock {
+                    stream_id: frame_header.stream_identifier,
+                    end_stream: headers.is_stream_end(),
+                    blocks: headers.payload.blocks,
+                    promised: None,
+                });
+                if end_headers {
+                    self.finish_header_block(conn)?;
+                }
+            }
+            FrameKind::Data => {
+                let data: DataFrame = self.expect_payload(conn, frame_header)?;
+                if data.payload.is_malformed() {
+                    self.send_go_away(conn)?;
+                }
+                let id = frame_header.stream_identifier;
+                self.consume_recv_window(conn, id, frame_header.length)?;
+                if let Some(stream) = self.streams.get_mut(&id) {
+                    if stream.reset.is_none() {
+                        if data.is_stream_end() {
+                            stream.state = State::Closed;
+                        }
+                        stream.response_data.extend(data.payload.blocks);
+                    }
+                }
+            }
+            FrameKind::Continuation => {
+                let continuation: ContinuationFrame = self.expect_payload(conn, frame_header)?;
+                let end_headers = continuation.is_end_headers();
+                match self.header_block.as_mut() {
+                    Some(block) if block.stream_id == frame_header.stream_identifier => {
+                        block.blocks.extend(continuation.payload.blocks)
+                    }
+                    _ => self.send_go_away(conn)?,
+                }
+                if end_headers {
+                    self.finish_header_block(conn)?;
+                }
+            }
+            FrameKind::Setting => {
+                self.update_settings(conn, frame_header)?;
+                self.flush_outgoing(conn)?;
+            }
+            FrameKind::WindowUpdate => {
+                self.handle_window_update(conn, frame_header)?;
+                self.flush_outgoing(conn)?;
+            }
+            FrameKind::RstStream => self.handle_stream_reset(conn, frame_header)?,
+            FrameKind::GoAway => self.handle_go_away(conn, frame_header)?,
+            FrameKind::Ping => return self.receive_ping(conn, frame_header).map(Some),
+            FrameKind::PushPromise => {
+                let promise: PushPromiseFrame = self.expect_payload(conn, frame_header)?;
+                if self.push_filter.is_none() {
+                    self.protocol_error(conn, "push promise received while push is disabled")?;
+                }
+                let end_headers = promise.is_end_headers();
+                self.header_block = Some(HeaderBlock {
+                    stream_id: frame_header.stream_identifier,
+                    end_stream: false,
+                    blocks: promise.payload.header_block_fragment,
+                    promised: Some(promise.payload.promised_stream_id),
+                });
+                if end_headers {
+                    self.finish_header_block(conn)?;
+                }
+            }
+            FrameKind::Priority => {
+                let _: PriorityFrame = self.expect_payload(conn, frame_header)?;
+            }
+            FrameKind::Altsvc => {}
+            FrameKind::Origin => {}
+        }
+        if !self.promised.is_empty() {
+            self.settle_pushes(conn)?;
+        }
+
+        Ok(None)
+    }
+
+    fn finish_header_block(&mut self, conn: &mut dyn Transport) -> Success {
+        let block = match self.header_block.take() {
+            Some(block) => block,
+            None => return Ok(()),
+        };
+        let headers = self.decompress_headers(&block.blocks)?;
+        if let Some(promised) = block.promised {
+            return self.receive_promise(conn, block.stream_id, promised, headers);
+        }
+        let interim = headers
+            .get(STATUS)
+            .and_then(|status| std::str::from_utf8(status).ok())
+            .and_then(|status| status.parse().ok())
+            .is_some_and(status::is_informational);
+        if let Some(stream) = self.streams.get_mut(&block.stream_id) {
+            stream.response_started = true;
+            if !stream.final_head {
+                stream.final_head = !interim;
+                stream.heads.push_back(headers);
+            }
+            if block.end_stream {
+                stream.state = State::Closed;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn receive_promise(
+        &mut self,
+        conn: &mut dyn Transport,
+        associated: u32,
+        promised: u32,
+        headers: HeaderMap,
+    ) -> Success {
+        let valid = promised.is_multiple_of(2)
+            && promised > self.last_promised
+            && !self.streams.contains_key(&promised)
+            && !associated.is_multiple_of(2)
+            && self.streams.contains_key(&associated);
+        if !valid {
+            return self.protocol_error(conn, "invalid push promise");
+        }
+        self.last_promised = promised;
+        let key = match headers.get(METHOD) {
+            Some(b"GET") => Self::promised_key(&headers),
+            _ => None,
+        };
+        let accepted = self
+            .push_filter
+            .as_ref()
+            .is_some_and(|filter| (filter.0)(&headers));
+        let stream = Stream {
+            recv_window: self.local.windows.stream as i64,
+            ..Stream::new(promised)
+        };
+        self.streams.insert(promised, stream);
+        match (key, accepted) {
+            (Some(key), true) => {
+                self.promised.insert(promised, key);
+
+                Ok(())
+            }
+            _ => {
+                self.reset_stream(conn, promised, ErrorCode::Cancel)?;
+                self.streams.remove(&promised);
+
+                Ok(())
+            }
+        }
+    }
+
+    fn promised_key(headers: &HeaderMap) -> Option<String> {
+        let url = format!(
+            "{}://{}{}",
+            headers.get_str(SCHEME)?,
+            headers.get_str(AUTHORITY)?,
+            headers.get_str(PATH)?
+        );
+
+        Url::parse(url).ok().map(|url| Self::push_key(&url))
+    }
+
+    fn push_key(url: &Url) -> String {
+        let mut key = format!("{}://{}", url.scheme, url.authority());
+        match url.resource.is_empty() {
+            true => key.push('/'),
+            false => key.push_str(&url.resource.as_utf8_lossy()),
+        }
+        if !url.query.is_empty() {
+            key.push('?');
+            key.push_str(&url.query.as_utf8_lossy());
+        }
+
+        key
+    }
+
+    fn settle_pushes(&mut self, conn: &mut dyn Transport) -> Success {
+        let ids: Vec<u32> = self.promised.keys().copied().collect();
+        for id in ids {
+            self.release_capacity(conn, id)?;
+            if !self.streams.get(&id).is_none_or(Stream::is_closed) {
+                continue;
+            }
+            let key = self.promised.remove(&id).unwrap_or_default();
+            let stream = self.streams.remove(&id).unwrap_or_default();
+            if stream.reset.is_some() {
+                continue;
+            }
+            let head = stream.heads.into_iter().find(|head| {
+                head.get_str(STATUS)
+                    .and_then(|status| status.parse().ok())
+                    .is_some_and(|status| !status::is_informational(status))
+            });
+            if let Some(Ok(mut response)) = head.map(|head| self.head_response(head)) {
+                response.body = stream.response_data;
+                self.cache_push(key, response);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cache_push(&mut self, key: String, response: Response) {
+        self.pushed
+            .retain(|_, (received, _)| received.elapsed() < PUSHED_MAX_AGE);
+        self.pushed.insert(key, (Instant::now(), response));
+        while self.pushed.len() > MAX_PUSHED
+            || self
+                .pushed
+                .values()
+                .map(|(_, response)| response.body.len())
+                .sum::<usize>()
+                > MAX_PUSHED_BYTES
+        {
+            let oldest = self
+                .pushed
+                .iter()
+                .min_by_key(|(_, (received, _))| *received)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.pushed.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    fn head_response(&self, mut headers: HeaderMap) -> Result<Response> {
         let status_code = headers
-            .get(":status")
+            .get(STATUS)
             .ok_or_else(|| Error::server("malformed response"))?;
+        let status_code = self.decode_status(status_code)?;
+        headers.retain(|name, _| !name.starts_with(b":"));
 
         Ok(Response {
             protocol: Default::default(),
-            status_code: self.decode_status(status_code.as_bytes())?,
+            status_code,
             headers,
-            body: stream.response_data,
+            body: Vec::new(),
+            compression: None,
         })
     }
 
-    pub fn ack_settings(stream: &mut StreamOwned<ClientConnection, TcpStream>) -> Success {
-        stream.write_all(&SettingsFrame::ack())?;
-        stream.flush()?;
+    fn local_settings(&self) -> Vec<Setting> {
+        let mut settings = vec![
+            Setting {
+                identifier: Identifier::HeaderTableSize,
+                value: self.local.header_table_size,
+            },
+            Setting {
+                identifier: Identifier::EnablePush,
+                value: self.push_filter.is_some() as u32,
+            },
+            Setting {
+                identifier: Identifier::InitialWindowSize,
+                value: self.local.windows.stream,
+            },
+            Setting {
+                identifier: Identifier::MaxFrameSize,
+                value: self.local.max_frame_size,
+            },
+        ];
+        if let Some(size) = self.local.max_header_list_size {
+            settings.push(Setting {
+                identifier: Identifier::MaxHeaderListSize,
+                value: size,
+            });
+        }
+
+        settings
+    }
+
+    fn next_frame_header(&mut self, conn: &mut dyn Transport) -> Result<FrameHeader> {
+        let sent = match self.settings_sent {
+            Some(sent) => sent,
+            None => return self.expect_frame_header(conn),
+        };
+        let remaining = match self.local.settings_timeout.checked_sub(sent.elapsed()) {
+            Some(remaining) if !remaining.is_zero() => remaining,
+            _ => return Self::settings_timeout(conn),
+        };
+        conn.set_read_timeout(Some(
+            self.read_timeout
+                .map_or(remaining, |read| read.min(remaining)),
+        ))?;
+        let frame_header = self.expect_frame_header(conn);
+        conn.set_read_timeout(self.read_timeout)?;
+        match frame_header {
+            Err(Error {
+                kind: ErrorKind::Timeout(TimeoutKind::Read, _),
+                ..
+            }) if sent.elapsed() >= self.local.settings_timeout => Self::settings_timeout(conn),
+            frame_header => frame_header,
+        }
+    }
+
+    fn settings_timeout<T>(conn: &mut dyn Transport) -> Result<T> {
+        Self::write_go_away(conn, ErrorCode::SettingsTimeout)?;
+
+        Err(Error::http2(
+            "settings were not acknowledged",
+            ErrorCode::SettingsTimeout,
+        ))
+    }
+
+    fn open_stream(&mut self) {
+        self.current = self.last_stream;
+        let stream = Stream {
+            send_window: self.settings.initial_window_size as i64,
+            recv_window: self.local.windows.stream as i64,
+            ..Stream::new(self.current)
+        };
+        self.streams.insert(self.current, stream);
+    }
+
+    fn send_capacity(&mut self, conn: &mut dyn Transport) -> Result<i64> {
+        loop {
+            let stream = self.current_stream()?;
+            if let Some(code) = stream.reset {
+                return Err(Self::reset_error(code));
+            }
+            let available = stream.send_window.min(self.server_window_size as i64);
+            if available > 0 {
+                return Ok(available);
+            }
+            self.decode_frame(conn)?;
+            let id = self.current;
+            self.release_capacity(conn, id)?;
+        }
+    }
+
+    fn consume_send_window(&mut self, id: u32, length: u32) {
+        self.server_window_size = self.server_window_size.saturating_sub(length);
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.send_window -= length as i64;
+        }
+    }
+
+    fn drain_outgoing(&mut self, id: u32) -> Result<Vec<u8>> {
+        let mut frames = Vec::new();
+        while let Some(stream) = self.streams.get_mut(&id) {
+            let outgoing = match (&mut stream.outgoing, stream.reset) {
+                (Some(outgoing), None) => outgoing,
+                _ => {
+                    stream.outgoing = None;
+                    break;
+                }
+            };
+            let available = stream
+                .send_window
+                .min(self.server_window_size as i64)
+                .min(self.settings.max_frame_size as i64)
+                .max(0) as usize;
+            if available == 0 && !outgoing.is_empty() {
+                break;
+            }
+            let chunk: Vec<u8> = outgoing.drain(..available.min(outgoing.len())).collect();
+            let flags = match outgoing.is_empty() {
+                true => {
+                    stream.outgoing = None;
+                    END_STREAM
+                }
+                false => 0,
+            };
+            frames.extend(
+                DataFrame::parse_from_payload(
+                    FrameHeader::new(FrameKind::Data, flags, id),
+                    &chunk,
+                )?
+                .encode(),
+            );
+            self.consume_send_window(id, chunk.len() as u32);
+        }
+
+        Ok(frames)
+    }
+
+    fn flush_outgoing(&mut self, conn: &mut dyn Transport) -> Success {
+        let mut ids: Vec<u32> = self
+            .streams
+            .values()
+            .filter(|stream| stream.outgoing.is_some())
+            .map(|stream| stream.id)
+            .collect();
+        ids.sort_unstable();
+        let mut frames = Vec::new();
+        for id in ids {
+            frames.extend(self.drain_outgoing(id)?);
+        }
+        if frames.is_empty() {
+            return Ok(());
+        }
+        conn.write_all(&frames).timeout_as(TimeoutKind::Write)?;
+        conn.flush().timeout_as(TimeoutKind::Write)?;
+
+        Ok(())
+    }
+
+    fn consume_recv_window(&mut self, conn: &mut dyn Transport, id: u32, length: u32) -> Success {
+        if length > self.client_window_size {
+            return self.flow_control_error(conn);
+        }
+        self.client_window_size -= length;
+        self.unreleased += length;
+        let exceeded = match self.streams.get_mut(&id) {
+            Some(stream) => {
+                stream.recv_window -= length as i64;
+                stream.unreleased += length;
+                stream.recv_window < 0
+            }
+            None => false,
+        };
+        match exceeded {
+            true => self.reset_stream(conn, id, ErrorCode::FlowControlError),
+            false => Ok(()),
+        }
+    }
+
+    fn open_connection_window(&mut self, conn: &mut dyn Transport) -> Success {
+        let increment = self
+            .local
+            .windows
+            .connection
+            .saturating_sub(self.client_window_size);
+        if increment == 0 {
+            return Ok(());
+        }
+        conn.write_all(&WindowUpdate::new(increment).to_frame().encode())
+            .timeout_as(TimeoutKind::Write)?;
+        conn.flush().timeout_as(TimeoutKind::Write)?;
+        self.client_window_size += increment;
+
+        Ok(())
+    }
+
+    fn reset_stream(&mut self, conn: &mut dyn Transport, id: u32, code: ErrorCode) -> Success {
+        conn.write_all(&RstStream::new(code).to_frame(id).encode())
+            .timeout_as(TimeoutKind::Write)?;
+        conn.flush().timeout_as(TimeoutKind::Write)?;
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.reset = Some(code);
+            stream.state = State::Closed;
+        }
+
+        Ok(())
+    }
+
+    fn current_stream(&mut self) -> Result<&mut Stream> {
+        self.streams
+            .get_mut(&self.current)
+            .ok_or_else(|| Error::client("no open stream"))
+    }
+
+    fn reset_error(code: ErrorCode) -> Error {
+        Error::connection("stream reset by server", code.some_box())
+    }
+
+    fn drive(
+        &mut self,
+        pending: &mut VecDeque<(usize, RequestBuilder)>,
+        open: &mut Exchanges,
+        conn: &mut dyn Transport,
+        complete: &mut dyn FnMut(usize, Result<Response>),
+    ) -> Success {
+        loop {
+            while open.len() < self.settings.max_concurrent_streams as usize {
+                let (index, request) = match pending.pop_front() {
+                    Some(next) => next,
+                    None => break,
+                };
+                let body = request.body_reader.clone();
+                match self.encode_request(request) {
+                    Ok(encoded) => {
+                        conn.write_all(&encoded).timeout_as(TimeoutKind::Write)?;
+                        open.insert(self.current, (index, None));
+                        if let Some(body) = body {
+                            let written = self.write_body(body, conn);
+                            if self.current_stream()?.reset.is_none() {
+                                written?;
+                            }
+                        }
+                    }
+                    Err(e) => complete(index, Err(e)),
+                }
+            }
+            conn.flush().timeout_as(TimeoutKind::Write)?;
+            if open.is_empty() {
+                return match pending.is_empty() {
+                    true => Ok(()),
+                    false => Err(Error::protocol("server does not accept new streams")),
+                };
+            }
+            self.decode_frame(conn)?;
+            self.collect(open, conn, complete)?;
+        }
+    }
+
+    fn collect(
+        &mut self,
+        open: &mut Exchanges,
+        conn: &mut dyn Transport,
+        complete: &mut dyn FnMut(usize, Result<Response>),
+    ) -> Success {
+        let ids: Vec<u32> = open.keys().copied().collect();
+        for id in ids {
+            self.release_capacity(conn, id)?;
+            let heads: Vec<HeaderMap> = self
+                .streams
+                .get_mut(&id)
+                .map(|stream| stream.heads.drain(..).collect())
+                .unwrap_or_default();
+            let mut failed = None;
+            if let Some((index, head)) = open.get_mut(&id) {
+                for headers in heads {
+                    match self.head_response(headers) {
+                        Ok(response) if status::is_informational(response.status_code) => {
+                            complete(*index, Ok(response))
+                        }
+                        Ok(response) => *head = Some(response),
+                        Err(e) => failed = Some(e),
+                    }
+                }
+            }
+            let done = self
+                .streams
+                .get(&id)
+                .is_none_or(|stream| stream.reset.is_some() || stream.is_closed());
+            if failed.is_none() && !done {
+                continue;
+            }
+            let stream = self.streams.remove(&id).unwrap_or_default();
+            let (index, head) = match open.remove(&id) {
+                Some(exchange) => exchange,
+                None => continue,
+            };
+            let response = match (failed, stream.reset, head) {
+                (Some(e), _, _) => Err(e),
+                (None, Some(code), _) => Err(Self::reset_error(code)),
+                (None, None, Some(mut response)) => {
+                    response.body = stream.response_data;
+                    Ok(response)
+                }
+                (None, None, None) => Err(Error::server("malformed response")),
+            };
+            complete(index, response);
+        }
+
+        Ok(())
+    }
+
+    fn release_capacity(&mut self, conn: &mut dyn Transport, id: u32) -> Success {
+        let mut frames = Vec::new();
+        if self.unreleased >= Self::release_threshold(self.local.windows.connection) {
+            frames.extend(WindowUpdate::new(self.unreleased).to_frame().encode());
+            self.client_window_size += self.unreleased;
+            self.unreleased = 0;
+        }
+        let threshold = Self::release_threshold(self.local.windows.stream);
+        if let Some(stream) = self.streams.get_mut(&id) {
+            if stream.unreleased >= threshold && !stream.is_closed() {
+                frames.extend(
+                    WindowUpdate::new(stream.unreleased)
+                        .to_stream_frame(id)
+                        .encode(),
+                );
+                stream.recv_window += stream.unreleased as i64;
+                stream.unreleased = 0;
+            }
+        }
+        if frames.is_empty() {
+            return Ok(());
+        }
+        conn.write_all(&frames).timeout_as(TimeoutKind::Write)?;
+        conn.flush().timeout_as(TimeoutKind::Write)?;
+
+        Ok(())
+    }
+
+    fn release_threshold(window: u32) -> u32 {
+        (window / 2).clamp(1, RELEASE_THRESHOLD)
+    }
+
+    pub fn ack_settings(stream: &mut dyn Transport) -> Success {
+        stream
+            .write_all(&SettingsFrame::ack())
+            .timeout_as(TimeoutKind::Write)?;
+        stream.flush().timeout_as(TimeoutKind::Write)?;
 
         Ok(())
     }
 
     pub fn update_settings(
         &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
+        stream: &mut dyn Transport,
         frame_header: FrameHeader,
     ) -> Success {
-        if frame_header.length == 0 && frame_header.flags & 0x1 != 0 {
+        if frame_header.flags & 0x1 != 0 {
+            if frame_header.length != 0 {
+                Self::write_go_away(stream, ErrorCode::FrameSizeError)?;
+                return Err(Error::http2(
+                    "settings acknowledgement has a payload",
+                    ErrorCode::FrameSizeError,
+                ));
+            }
+            self.settings_sent = None;
             return Ok(());
         }
         let frame: SettingsFrame = self.expect_payload(stream, frame_header)?;
         if frame.payload.is_malformed() {
             self.send_go_away(stream)?;
         }
+        let initial_window_size = self.settings.initial_window_size;
         self.settings.update(frame.payload);
+        let delta = self.settings.initial_window_size as i64 - initial_window_size as i64;
+        for open in self.streams.values_mut() {
+            open.send_window += delta;
+        }
+        if self
+            .streams
+            .values()
+            .any(|open| open.send_window > MAX_WINDOW_SIZE as i64)
+        {
+            return self.flow_control_error(stream);
+        }
 
         Self::ack_settings(stream)
     }
 
     fn handle_window_update(
         &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
+        stream: &mut dyn Transport,
         frame_header: FrameHeader,
     ) -> Success {
         let frame: WindowUpdateFrame = self.expect_payload(stream, frame_header)?;
         if frame.payload.is_malformed() {
             self.send_go_away(stream)?;
         }
-        self.server_window_size = frame.payload.window_size_increment;
-
-        Ok(())
-    }
-
-    fn update_window(&mut self, stream: &mut StreamOwned<ClientConnection, TcpStream>) -> Success {
-        self.client_window_size += self.settings.initial_window_size * 4;
-        let frame = WindowUpdate::new(self.client_window_size)
-            .to_frame()
-            .encode();
-        stream.write_all(&frame)?;
-        stream.flush()?;
+        let increment = frame.payload.window_size_increment & MAX_WINDOW_SIZE;
+        match frame_header.stream_identifier {
+            0 => match self
+                .server_window_size
+                .checked_add(increment)
+                .filter(|window| *window <= MAX_WINDOW_SIZE)
+            {
+                Some(window) => self.server_window_size = window,
+                None => return self.flow_control_error(stream),
+            },
+            id => {
+                let exceeded = match self.streams.get_mut(&id) {
+                    Some(open) => {
+                        open.send_window += increment as i64;
+                        open.send_window > MAX_WINDOW_SIZE as i64
+                    }
+                    None => false,
+                };
+                if exceeded {
+                    self.reset_stream(stream, id, ErrorCode::FlowControlError)?;
+                }
+            }
+        }
 
         Ok(())
     }
@@ -299,7 +1078,7 @@ impl<'a> Http2Codec<'a> {
 
     pub fn receive_ping(
         &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
+        stream: &mut dyn Transport,
         frame_header: FrameHeader,
     ) -> Result<Response> {
         if frame_header.flags & ping::Flags::Ack as u8 == 0x0 {
@@ -319,27 +1098,25 @@ impl<'a> Http2Codec<'a> {
             status_code: 200,
             headers: Default::default(),
             body: received.to_be_bytes().to_vec(),
+            compression: None,
         })
     }
 
     fn handle_stream_reset(
         &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
+        stream: &mut dyn Transport,
         frame_header: FrameHeader,
     ) -> Success {
         let frame: RstStreamFrame = self.expect_payload(stream, frame_header)?;
+        if let Some(stream) = self.streams.get_mut(&frame_header.stream_identifier) {
+            stream.reset = Some(frame.payload.error_code);
+            stream.state = State::Closed;
+        }
 
-        Err(Error::connection(
-            "stream reset by server",
-            frame.payload.error_code.some_box(),
-        ))
+        Ok(())
     }
 
-    fn handle_go_away(
-        &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
-        frame_header: FrameHeader,
-    ) -> Success {
+    fn handle_go_away(&mut self, stream: &mut dyn Transport, frame_header: FrameHeader) -> Success {
         let frame: GoAwayFrame = self.expect_payload(stream, frame_header)?;
         let error_message = match frame.payload.additional_debug_data.is_empty() {
             true => "connection reset by server".to_string(),
@@ -356,21 +1133,36 @@ impl<'a> Http2Codec<'a> {
         ))
     }
 
-    fn send_go_away(&mut self, stream: &mut StreamOwned<ClientConnection, TcpStream>) -> Success {
-        let frame = GoAway::new(ErrorCode::ConnectError, None)
-            .to_frame()
-            .encode();
-        stream.write_all(&frame)?;
-        stream.flush()?;
+    fn send_go_away(&mut self, stream: &mut dyn Transport) -> Success {
+        Self::write_go_away(stream, ErrorCode::ConnectError)?;
 
         Err(Error::server("received malformed frame"))
     }
 
-    fn try_read_buf<T>(
-        &mut self,
-        stream: &mut StreamOwned<ClientConnection, TcpStream>,
-        size: T,
-    ) -> Result<Vec<u8>>
+    fn protocol_error(&mut self, stream: &mut dyn Transport, message: &str) -> Success {
+        Self::write_go_away(stream, ErrorCode::ProtocolError)?;
+
+        Err(Error::http2(message, ErrorCode::ProtocolError))
+    }
+
+    fn flow_control_error(&mut self, stream: &mut dyn Transport) -> Success {
+        Self::write_go_away(stream, ErrorCode::FlowControlError)?;
+
+        Err(Error::http2(
+            "flow control window exceeded",
+            ErrorCode::FlowControlError,
+        ))
+    }
+
+    fn write_go_away(stream: &mut dyn Transport, code: ErrorCode) -> Success {
+        let frame = GoAway::new(code, None).to_frame().encode();
+        stream.write_all(&frame).timeout_as(TimeoutKind::Write)?;
+        stream.flush().timeout_as(TimeoutKind::Write)?;
+
+        Ok(())
+    }
+
+    fn try_read_buf<T>(&mut self, stream: &mut dyn Transport, size: T) -> Result<Vec<u8>>
     where
         T: TryInto<usize>,
     {
@@ -382,7 +1174,7 @@ impl<'a> Http2Codec<'a> {
         ];
         stream
             .read_exact(&mut buffer)
-            .map_err(|e| Error::connection(&e.to_string(), e.some_box()))?;
+            .timeout_as(TimeoutKind::Read)?;
 
         Ok(buffer)
     }
@@ -419,7 +1211,7 @@ impl Default for StreamSettings {
     fn default() -> Self {
         Self {
             header_table_size: 4096,
-            enable_push: true,
+            enable_push: false,
             max_concurrent_streams: 100,
             initial_window_size: 65535,
             max_frame_size: 16384,
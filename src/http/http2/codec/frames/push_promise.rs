@@ -3,11 +3,17 @@ use super::*;
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub struct PushPromise {
     pad_length: Option<u8>,
-    promised_stream_id: u32,
-    header_block_fragment: Vec<u8>,
+    pub promised_stream_id: u32,
+    pub header_block_fragment: Vec<u8>,
     padding: Option<Vec<u8>>,
 }
 
+impl Frame<PushPromise> {
+    pub fn is_end_headers(&self) -> bool {
+        self.header.flags & Flags::EndHeaders as u8 != 0
+    }
+}
+
 impl FramePayload for PushPromise {
     fn parse(bytes: &[u8], flags: u8) -> crate::http::Result<Self> {
         let mut iter = bytes.into_iter();
@@ -51,6 +57,6 @@ impl FramePayload for PushPromise {
 
 #[repr(u8)]
 pub enum Flags {
-    _EndHeaders = 0x4,
+    EndHeaders = 0x4,
     Padded = 0x8,
 }
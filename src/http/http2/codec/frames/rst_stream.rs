@@ -5,6 +5,18 @@ pub struct RstStream {
     pub error_code: ErrorCode,
 }
 
+impl RstStream {
+    pub fn new(error_code: ErrorCode) -> Self {
+        Self { error_code }
+    }
+    pub(crate) fn to_frame(self, stream_id: u32) -> Frame<Self> {
+        Frame {
+            header: FrameHeader::new(FrameKind::RstStream, 0, stream_id),
+            payload: self,
+        }
+    }
+}
+
 impl FramePayload for RstStream {
     fn parse(bytes: &[u8], _flags: u8) -> Result<Self> {
         let bytes = <[u8; 4]>::try_from(bytes).map_err(|_| Error::server("invalid frame"))?;
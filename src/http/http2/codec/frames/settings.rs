@@ -101,7 +101,7 @@ impl Setting {
     fn is_malformed(&self) -> bool {
         match &self.identifier {
             Identifier::InitialWindowSize => self.value > 2147483647,
-            Identifier::MaxFrameSize => self.value > 16777215,
+            Identifier::MaxFrameSize => !(16384..=16777215).contains(&self.value),
             _ => false,
         }
     }
@@ -14,6 +14,12 @@ impl WindowUpdate {
     pub(crate) fn to_frame(self) -> Frame<Self> {
         self.into()
     }
+    pub(crate) fn to_stream_frame(self, stream_id: u32) -> Frame<Self> {
+        Frame {
+            header: FrameHeader::new(FrameKind::WindowUpdate, 0, stream_id),
+            payload: self,
+        }
+    }
 }
 
 impl From<WindowUpdate> for Frame<WindowUpdate> {
@@ -0,0 +1,641 @@
+use crate::http::http2::codec::frames::*;
+use crate::http::options::ConnOptions;
+use crate::http::proto_conn::ProtoConn;
+use crate::http::request::RequestBuilder;
+use crate::http::test_utils::{
+    duplex, http2_data, http2_handshake, http2_headers, http2_response, read_frame,
+    read_header_frames, read_preface, MemoryConnector,
+};
+use crate::http::url::Url;
+use crate::http::{HttpClient, Protocol};
+use std::io::Write;
+use std::sync::Mutex;
+use std::thread;
+
+#[test]
+fn http2_streams_are_multiplexed() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(
+            &mut server,
+            vec![settings::Setting {
+                identifier: settings::Identifier::MaxConcurrentStreams,
+                value: 2,
+            }],
+        );
+        let mut opened = read_header_frames(&mut server, 2);
+        let mut frames = RstStreamFrame::new(
+            FrameHeader::new(FrameKind::RstStream, 0, 3),
+            rst_stream::RstStream {
+                error_code: ErrorCode::RefusedStream,
+            },
+        )
+        .encode();
+        frames.extend(http2_response(1, b"one"));
+        server.write_all(&frames).unwrap();
+        opened.extend(read_header_frames(&mut server, 1));
+        server.write_all(&http2_response(5, b"five")).unwrap();
+
+        opened
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new().connector(connector);
+    let completions = client
+        .send_concurrent(
+            ["one", "two", "three"]
+                .iter()
+                .map(|path| {
+                    RequestBuilder::get(&format!("http://memory.test/{path}"))
+                        .protocol(Protocol::HTTP2)
+                })
+                .collect(),
+        )
+        .unwrap();
+    let completed: Vec<(usize, Option<Vec<u8>>)> = completions
+        .map(|(index, response)| (index, response.ok().map(|response| response.body)))
+        .collect();
+    assert_eq!(
+        completed,
+        vec![
+            (1, None),
+            (0, Some(b"one".to_vec())),
+            (2, Some(b"five".to_vec())),
+        ]
+    );
+    assert_eq!(handle.join().unwrap(), vec![1, 3, 5]);
+}
+
+#[test]
+fn http2_upload_waits_for_window_updates() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(
+            &mut server,
+            vec![settings::Setting {
+                identifier: settings::Identifier::InitialWindowSize,
+                value: 1000,
+            }],
+        );
+        let mut data_frames = Vec::new();
+        let mut uploaded = Vec::new();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind != FrameKind::Data {
+                continue;
+            }
+            data_frames.push(header.length);
+            uploaded.extend(payload);
+            if uploaded.len() == 1000 {
+                let mut updates = Vec::new();
+                for _ in 0..2 {
+                    updates.extend(
+                        window_update::WindowUpdate::new(1000)
+                            .to_stream_frame(1)
+                            .encode(),
+                    );
+                }
+                server.write_all(&updates).unwrap();
+            }
+            if header.flags & END_STREAM != 0 {
+                break;
+            }
+        }
+        server.write_all(&http2_response(1, b"")).unwrap();
+
+        (data_frames, uploaded)
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP2,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let source: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+    let response = conn
+        .send_request(
+            RequestBuilder::post("http://memory.test/")
+                .protocol(Protocol::HTTP2)
+                .body_reader(std::io::Cursor::new(source.clone()), Some(3000)),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    let (data_frames, uploaded) = handle.join().unwrap();
+    assert_eq!(data_frames, vec![1000, 1000, 1000]);
+    assert_eq!(uploaded, source);
+}
+
+#[test]
+fn http2_upload_releases_echoed_data() {
+    use crate::http::Http2Settings;
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(
+            &mut server,
+            vec![settings::Setting {
+                identifier: settings::Identifier::InitialWindowSize,
+                value: 1000,
+            }],
+        );
+        let mut frames = http2_headers(1, 0, vec![0x88]);
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind != FrameKind::Data {
+                continue;
+            }
+            let end_stream = header.flags & END_STREAM;
+            frames.extend(http2_data(1, end_stream, &payload));
+            server.write_all(&std::mem::take(&mut frames)).unwrap();
+            if end_stream != 0 {
+                return server;
+            }
+            loop {
+                let (header, _) = read_frame(&mut server);
+                if header.kind == FrameKind::WindowUpdate && header.stream_identifier == 1 {
+                    break;
+                }
+            }
+            server
+                .write_all(
+                    &window_update::WindowUpdate::new(1000)
+                        .to_stream_frame(1)
+                        .encode(),
+                )
+                .unwrap();
+        }
+    });
+    let options =
+        ConnOptions::default().http2_settings(Http2Settings::new().initial_window_size(1000));
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP2, &options).unwrap();
+    let source: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+    let response = conn
+        .send_request(
+            RequestBuilder::post("http://memory.test/")
+                .protocol(Protocol::HTTP2)
+                .body_reader(std::io::Cursor::new(source.clone()), Some(3000)),
+        )
+        .unwrap();
+    assert_eq!(response.body, source);
+    handle.join().unwrap();
+}
+
+#[test]
+fn http2_receive_window_is_enforced() {
+    use crate::http::Http2Settings;
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let advertised = http2_handshake(&mut server, Vec::new());
+        let mut connection_window = 0;
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            match header.kind {
+                FrameKind::WindowUpdate if header.stream_identifier == 0 => {
+                    connection_window = u32::from_be_bytes(payload.try_into().unwrap())
+                }
+                FrameKind::Headers => break,
+                _ => {}
+            }
+        }
+        let mut frames = http2_headers(1, 0, vec![0x88]);
+        frames.extend(http2_data(1, 0, &[0; 1200]));
+        server.write_all(&frames).unwrap();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind == FrameKind::RstStream {
+                return (
+                    advertised,
+                    connection_window,
+                    header.stream_identifier,
+                    payload,
+                );
+            }
+        }
+    });
+    let options = ConnOptions::default().http2_settings(
+        Http2Settings::new()
+            .connection_window_size(1 << 20)
+            .initial_window_size(1000),
+    );
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP2, &options).unwrap();
+    let result =
+        conn.send_request(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2));
+    assert!(result.is_err());
+    let (advertised, connection_window, stream, code) = handle.join().unwrap();
+    assert_eq!(advertised, vec![(1, 4096), (2, 0), (4, 1000), (5, 16_384)]);
+    assert_eq!(connection_window, (1 << 20) - 65_535);
+    assert_eq!(stream, 1);
+    assert_eq!(code, (ErrorCode::FlowControlError as u32).to_be_bytes());
+}
+
+#[test]
+fn http2_buffered_body_split_by_frame_size_and_window() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(
+            &mut server,
+            vec![settings::Setting {
+                identifier: settings::Identifier::InitialWindowSize,
+                value: 20_000,
+            }],
+        );
+        let mut data_frames = Vec::new();
+        let mut uploaded = Vec::new();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind != FrameKind::Data {
+                continue;
+            }
+            data_frames.push((header.length, header.flags & END_STREAM != 0));
+            uploaded.extend(payload);
+            if uploaded.len() == 20_000 {
+                let update = window_update::WindowUpdate::new(20_000).to_stream_frame(1);
+                server.write_all(&update.encode()).unwrap();
+            }
+            if header.flags & END_STREAM != 0 {
+                break;
+            }
+        }
+        server.write_all(&http2_response(1, b"")).unwrap();
+
+        (data_frames, uploaded)
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP2,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let source: Vec<u8> = (0..40_000).map(|i| (i % 251) as u8).collect();
+    let response = conn
+        .send_request(
+            RequestBuilder::post("http://memory.test/")
+                .protocol(Protocol::HTTP2)
+                .body(&source),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    let (data_frames, uploaded) = handle.join().unwrap();
+    assert_eq!(
+        data_frames,
+        vec![
+            (16_384, false),
+            (3_616, false),
+            (16_384, false),
+            (3_616, true)
+        ]
+    );
+    assert_eq!(uploaded, source);
+}
+
+#[test]
+fn http2_large_header_block_uses_continuation() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        let mut kinds = Vec::new();
+        let mut block = Vec::new();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if !matches!(header.kind, FrameKind::Headers | FrameKind::Continuation) {
+                continue;
+            }
+            assert!(header.length <= 16_384);
+            kinds.push(header.kind);
+            block.extend(payload);
+            if header.flags & headers::Flags::EndHeaders as u8 != 0 {
+                break;
+            }
+        }
+        server.write_all(&http2_response(1, b"")).unwrap();
+        let decoded = hpack::Decoder::new().decode(&block).unwrap();
+
+        (kinds, decoded)
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP2,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let token: String = (0..40_000)
+        .map(|i| char::from(b'!' + (i * 7 % 90) as u8))
+        .collect();
+    let response = conn
+        .send_request(
+            RequestBuilder::get("http://memory.test/")
+                .protocol(Protocol::HTTP2)
+                .header((b"x-token", token.as_bytes())),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    let (kinds, decoded) = handle.join().unwrap();
+    assert_eq!(kinds[0], FrameKind::Headers);
+    assert!(kinds.len() >= 3);
+    assert!(kinds[1..]
+        .iter()
+        .all(|kind| *kind == FrameKind::Continuation));
+    assert!(decoded
+        .iter()
+        .any(|(name, value)| name == b"x-token" && value == token.as_bytes()));
+}
+
+#[test]
+fn http2_client_settings_are_advertised() {
+    use crate::http::Http2Settings;
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let advertised = http2_handshake(&mut server, Vec::new());
+        read_header_frames(&mut server, 1);
+        server.write_all(&http2_response(1, &[7; 20_000])).unwrap();
+
+        (advertised, server)
+    });
+    let options = ConnOptions::default().http2_settings(
+        Http2Settings::new()
+            .header_table_size(8192)
+            .max_frame_size(32_768)
+            .max_header_list_size(16_384),
+    );
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP2, &options).unwrap();
+    let response = conn
+        .send_request(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2))
+        .unwrap();
+    assert_eq!(response.body.len(), 20_000);
+    let (advertised, _server) = handle.join().unwrap();
+    assert_eq!(
+        advertised,
+        vec![(1, 8192), (2, 0), (4, 65_535), (5, 32_768), (6, 16_384)]
+    );
+}
+
+#[test]
+fn http2_unacknowledged_settings_time_out() {
+    use crate::http::{ErrorKind, Http2Settings};
+    use std::time::Duration;
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        read_preface(&mut server);
+        server.write_all(&SettingsFrame::empty()).unwrap();
+        read_header_frames(&mut server, 1);
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind == FrameKind::GoAway {
+                return payload[4..8].to_vec();
+            }
+        }
+    });
+    let options = ConnOptions::default()
+        .http2_settings(Http2Settings::new().settings_timeout(Duration::from_millis(50)));
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP2, &options).unwrap();
+    let error = conn
+        .send_request(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2))
+        .unwrap_err();
+    assert!(matches!(
+        error.kind,
+        ErrorKind::Http2Protocol(ErrorCode::SettingsTimeout)
+    ));
+    assert_eq!(
+        handle.join().unwrap(),
+        (ErrorCode::SettingsTimeout as u32).to_be_bytes()
+    );
+}
+
+#[test]
+fn http2_settings_ack_with_payload_is_rejected() {
+    use crate::http::ErrorKind;
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        read_preface(&mut server);
+        let mut frames = SettingsFrame::empty();
+        frames.extend(
+            SettingsFrame::new(
+                FrameHeader::new(FrameKind::Setting, 0x1, 0),
+                vec![settings::Setting {
+                    identifier: settings::Identifier::MaxConcurrentStreams,
+                    value: 1,
+                }],
+            )
+            .encode(),
+        );
+        server.write_all(&frames).unwrap();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind == FrameKind::GoAway {
+                return payload[4..8].to_vec();
+            }
+        }
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP2,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let error = conn
+        .send_request(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2))
+        .unwrap_err();
+    assert!(matches!(
+        error.kind,
+        ErrorKind::Http2Protocol(ErrorCode::FrameSizeError)
+    ));
+    assert_eq!(
+        handle.join().unwrap(),
+        (ErrorCode::FrameSizeError as u32).to_be_bytes()
+    );
+}
+
+fn push_promise(stream: u32, promised: u32, path: &str) -> Vec<u8> {
+    let mut payload = promised.to_be_bytes().to_vec();
+    payload.extend(hpack::Encoder::new().encode(vec![
+        (b":method".as_slice(), b"GET".as_slice()),
+        (b":scheme".as_slice(), b"http".as_slice()),
+        (b":authority".as_slice(), b"memory.test".as_slice()),
+        (b":path".as_slice(), path.as_bytes()),
+    ]));
+
+    PushPromiseFrame::parse_from_payload(
+        FrameHeader::new(
+            FrameKind::PushPromise,
+            push_promise::Flags::EndHeaders as u8,
+            stream,
+        ),
+        &payload,
+    )
+    .unwrap()
+    .encode()
+}
+
+#[test]
+fn http2_pushed_response_is_served_from_cache() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let settings = http2_handshake(&mut server, Vec::new());
+        let mut opened = read_header_frames(&mut server, 1);
+        let mut frames = push_promise(1, 2, "/style.css");
+        frames.extend(http2_response(2, b"pushed"));
+        frames.extend(http2_response(1, b"index"));
+        server.write_all(&frames).unwrap();
+        opened.extend(read_header_frames(&mut server, 1));
+        server.write_all(&http2_response(3, b"other")).unwrap();
+
+        (settings, opened, server)
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new().connector(connector).on_push(|_| true);
+    let mut get = |path: &str| {
+        client
+            .execute(
+                RequestBuilder::get(&format!("http://memory.test{path}")).protocol(Protocol::HTTP2),
+            )
+            .unwrap()
+            .body
+    };
+    assert_eq!(get("/index.html"), b"index");
+    assert_eq!(get("/style.css"), b"pushed");
+    assert_eq!(get("/other"), b"other");
+    let (settings, opened, _server) = handle.join().unwrap();
+    assert_eq!(settings[1], (2, 1));
+    assert_eq!(opened, vec![1, 3]);
+}
+
+fn push_error(promises: Vec<u8>, options: ConnOptions) -> (ErrorCode, Vec<u8>) {
+    use crate::http::ErrorKind;
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        read_header_frames(&mut server, 1);
+        server.write_all(&promises).unwrap();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind == FrameKind::GoAway {
+                return payload[4..8].to_vec();
+            }
+        }
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP2, &options).unwrap();
+    let error = conn
+        .send_request(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2))
+        .unwrap_err();
+    let code = match error.kind {
+        ErrorKind::Http2Protocol(code) => code,
+        kind => panic!("unexpected error {kind:?}"),
+    };
+
+    (code, handle.join().unwrap())
+}
+
+#[test]
+fn http2_invalid_push_promises_are_protocol_errors() {
+    let protocol_error = (
+        ErrorCode::ProtocolError,
+        (ErrorCode::ProtocolError as u32).to_be_bytes().to_vec(),
+    );
+    let mut reused = push_promise(1, 2, "/style.css");
+    reused.extend(push_promise(1, 2, "/script.js"));
+    let promises = [
+        reused,
+        push_promise(1, 3, "/style.css"),
+        push_promise(5, 2, "/style.css"),
+    ];
+    for promises in promises {
+        let options = ConnOptions::default().on_push(|_| true);
+        assert_eq!(push_error(promises, options), protocol_error);
+    }
+    assert_eq!(
+        push_error(push_promise(1, 2, "/style.css"), ConnOptions::default()),
+        protocol_error
+    );
+}
+
+#[test]
+fn http2_push_cache_drops_the_oldest_entries() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        read_header_frames(&mut server, 1);
+        let mut frames = Vec::new();
+        for index in 0..33 {
+            let promised = 2 * (index + 1);
+            frames.extend(push_promise(1, promised, &format!("/push/{index}")));
+            frames.extend(http2_response(promised, b"pushed"));
+        }
+        frames.extend(http2_response(1, b"index"));
+        server.write_all(&frames).unwrap();
+        let opened = read_header_frames(&mut server, 1);
+        server.write_all(&http2_response(3, b"fresh")).unwrap();
+
+        (opened, server)
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new().connector(connector).on_push(|_| true);
+    let mut get = |path: &str| {
+        client
+            .execute(
+                RequestBuilder::get(&format!("http://memory.test{path}")).protocol(Protocol::HTTP2),
+            )
+            .unwrap()
+            .body
+    };
+    assert_eq!(get("/index.html"), b"index");
+    assert_eq!(get("/push/32"), b"pushed");
+    assert_eq!(get("/push/0"), b"fresh");
+    let (opened, _server) = handle.join().unwrap();
+    assert_eq!(opened, vec![3]);
+}
+
+#[test]
+fn http2_rejected_push_is_cancelled() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        read_header_frames(&mut server, 1);
+        server.write_all(&push_promise(1, 2, "/style.css")).unwrap();
+        let (header, payload) = loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind == FrameKind::RstStream {
+                break (header, payload);
+            }
+        };
+        let mut frames = http2_response(2, b"pushed");
+        frames.extend(http2_response(1, b"index"));
+        server.write_all(&frames).unwrap();
+
+        (header.stream_identifier, payload, server)
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new()
+        .connector(connector)
+        .on_push(|headers| headers.get_str(":path") != Some("/style.css"));
+    let response = client
+        .execute(RequestBuilder::get("http://memory.test/index.html").protocol(Protocol::HTTP2))
+        .unwrap();
+    assert_eq!(response.body, b"index");
+    let (stream, code, _server) = handle.join().unwrap();
+    assert_eq!(stream, 2);
+    assert_eq!(code, (ErrorCode::Cancel as u32).to_be_bytes());
+}
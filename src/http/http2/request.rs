@@ -1,4 +1,6 @@
+use crate::http::header::CONTENT_LENGTH;
 use crate::http::request::RequestBuilder;
+use crate::http::url::Scheme;
 use crate::http::utf8_utils::UTF8Utils;
 use crate::http::Method;
 
@@ -11,20 +13,17 @@ pub struct Request {
 impl Request {
     fn default_headers(
         method: Method,
+        scheme: Scheme,
         authority: &[u8],
         resource: &[u8],
         query: Vec<u8>,
         custom_capacity: usize,
     ) -> Vec<(Vec<u8>, Vec<u8>)> {
         let mut headers = Vec::with_capacity(custom_capacity + 5);
-        let method: (Vec<u8>, Vec<u8>) = match method {
-            Method::GET => to_owned_header(headers::GET),
-            Method::POST => to_owned_header(headers::POST),
-            Method::PUT => to_owned_header(headers::PUT),
-            Method::PATCH => to_owned_header(headers::PATCH),
-            Method::DELETE => to_owned_header(headers::DELETE),
-            Method::CONNECT => to_owned_header(headers::CONNECT),
-        };
+        let method = (
+            headers::METHOD.to_vec(),
+            method.as_str().as_bytes().to_vec(),
+        );
         let mut resource = match resource.is_empty() {
             true => b"/".to_vec(),
             false => resource.to_vec(),
@@ -36,7 +35,10 @@ impl Request {
         headers.extend(vec![
             method,
             (headers::PATH.to_vec(), resource),
-            to_owned_header(headers::SCHEME_HTTPS),
+            match scheme {
+                Scheme::Http | Scheme::Unix => to_owned_header(headers::SCHEME_HTTP),
+                Scheme::Https => to_owned_header(headers::SCHEME_HTTPS),
+            },
             (headers::AUTHORITY.to_vec(), authority.to_vec()),
         ]);
 
@@ -48,11 +50,22 @@ impl From<RequestBuilder> for Request {
     fn from(builder: RequestBuilder) -> Self {
         let mut headers = Self::default_headers(
             builder.method,
-            &builder.url.host,
+            builder.url.scheme,
+            &builder.url.host_header(),
             &builder.url.resource,
             builder.url.query,
             builder.headers.len(),
         );
+        let length = match (&builder.body, &builder.body_reader) {
+            (Some(data), _) => Some(data.len() as u64),
+            (None, Some(reader)) => reader.length(),
+            (None, None) => None,
+        };
+        if let Some(length) = length {
+            if !builder.headers.contains(CONTENT_LENGTH) {
+                headers.push((CONTENT_LENGTH.to_lower(), length.to_string().into_bytes()));
+            }
+        }
         headers.extend(builder.headers.into_iter().map(|(k, v)| (k.to_lower(), v)));
 
         Self {
@@ -70,10 +83,15 @@ pub mod headers {
     pub const PATCH: (&[u8], &[u8]) = (METHOD, b"PATCH");
     pub const DELETE: (&[u8], &[u8]) = (METHOD, b"DELETE");
     pub const CONNECT: (&[u8], &[u8]) = (METHOD, b"CONNECT");
+    pub const HEAD: (&[u8], &[u8]) = (METHOD, b"HEAD");
+    pub const OPTIONS: (&[u8], &[u8]) = (METHOD, b"OPTIONS");
+    pub const TRACE: (&[u8], &[u8]) = (METHOD, b"TRACE");
     pub const AUTHORITY: &[u8] = b":authority";
     pub const PATH: &[u8] = b":path";
+    pub const STATUS: &[u8] = b":status";
     pub const SCHEME: &[u8] = b":scheme";
     pub const SCHEME_HTTPS: (&[u8], &[u8]) = (SCHEME, b"https");
+    pub const SCHEME_HTTP: (&[u8], &[u8]) = (SCHEME, b"http");
 }
 
 fn to_owned_header(header: (&[u8], &[u8])) -> (Vec<u8>, Vec<u8>) {
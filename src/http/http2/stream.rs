@@ -1,10 +1,21 @@
+use crate::http::http2::codec::frames::ErrorCode;
+use crate::http::options::DEFAULT_WINDOW_SIZE;
+use crate::http::HeaderMap;
+use std::collections::VecDeque;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stream {
     pub id: u32,
     pub state: State,
-    pub response_headers: Vec<u8>,
+    pub heads: VecDeque<HeaderMap>,
+    pub response_started: bool,
+    pub final_head: bool,
     pub response_data: Vec<u8>,
-    pub termination_code: u8,
+    pub outgoing: Option<Vec<u8>>,
+    pub unreleased: u32,
+    pub send_window: i64,
+    pub recv_window: i64,
+    pub reset: Option<ErrorCode>,
 }
 
 impl Stream {
@@ -12,9 +23,15 @@ impl Stream {
         Self {
             id,
             state: State::Open,
-            response_headers: Vec::new(),
+            heads: VecDeque::new(),
+            response_started: false,
+            final_head: false,
             response_data: Vec::new(),
-            termination_code: 0,
+            outgoing: None,
+            unreleased: 0,
+            send_window: DEFAULT_WINDOW_SIZE as i64,
+            recv_window: DEFAULT_WINDOW_SIZE as i64,
+            reset: None,
         }
     }
     pub fn is_closed(&self) -> bool {
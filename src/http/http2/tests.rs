@@ -1,7 +1,11 @@
 use crate::http::request::headers::{values::ALL, ACCEPT};
 use crate::http::request::RequestBuilder;
 use crate::http::test_utils::*;
-use crate::http::HttpClient;
+use crate::http::{HttpClient, Protocol};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
 
 #[test]
 fn foreknowledge_get() {
@@ -48,3 +52,58 @@ fn ping() {
         assert!(client.ping().is_ok());
     }
 }
+
+#[test]
+fn h2c_prior_knowledge() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        http2_handshake(&mut stream, Vec::new());
+        stream.write_all(&http2_response(1, b"hello")).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        let mut sink = [0; 1024];
+        while let Ok(read) = stream.read(&mut sink) {
+            if read == 0 {
+                break;
+            }
+        }
+    });
+    let mut client = HttpClient::new();
+    let request =
+        RequestBuilder::get(&format!("http://127.0.0.1:{port}/")).protocol(Protocol::HTTP2);
+    let response = client.execute(request).unwrap();
+    assert_eq!(response.protocol, Protocol::HTTP2);
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body, b"hello");
+    drop(client);
+    server.join().unwrap();
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn compressed_body_sets_length_and_encoding() {
+    use crate::http::encoding::{self, BodyCompression, ContentEncoding};
+    use crate::http::http2::request::Request;
+    let mut builder = RequestBuilder::post("http://example.test/ingest")
+        .body(&[b'a'; 4096])
+        .compress(BodyCompression::new(ContentEncoding::Gzip));
+    encoding::compress_body(&mut builder).unwrap();
+    let request = Request::from(builder);
+    let data = request.data.unwrap();
+    assert!(data.len() < 4096);
+    let header = |name: &[u8]| {
+        request
+            .raw_headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    assert_eq!(header(b"content-encoding"), Some(b"gzip".to_vec()));
+    assert_eq!(
+        header(b"content-length"),
+        Some(data.len().to_string().into_bytes())
+    );
+}
@@ -0,0 +1,352 @@
+use crate::http::encoding::BodyCompression;
+use crate::http::error::{IoTimeout, SomeError, TimeoutKind};
+use crate::http::proxy::Proxy;
+use crate::http::tls::TlsOptions;
+use crate::http::transport::{Connector, SharedConnector};
+use crate::http::url::Url;
+#[cfg(feature = "http2")]
+use crate::http::HeaderMap;
+use crate::http::{Error, Response, Result};
+use std::fmt::{Debug, Formatter};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests;
+
+pub const READ_TIMEOUT: Duration = Duration::from_secs(3);
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+pub const MAX_WINDOW_SIZE: u32 = 2_147_483_647;
+pub const MIN_FRAME_SIZE: u32 = 16_384;
+pub const MAX_FRAME_SIZE: u32 = 16_777_215;
+pub const SETTINGS_TIMEOUT: Duration = Duration::from_secs(10);
+pub const EXPECT_CONTINUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Timeouts {
+    pub(crate) connect: Option<Duration>,
+    pub(crate) read: Option<Duration>,
+    pub(crate) deadline: Option<Duration>,
+    pub(crate) idle: Duration,
+    pub(crate) expect_continue: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: None,
+            read: Some(READ_TIMEOUT),
+            deadline: None,
+            idle: IDLE_TIMEOUT,
+            expect_continue: EXPECT_CONTINUE_TIMEOUT,
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connect(mut self, timeout: Duration) -> Self {
+        self.connect = Some(timeout);
+
+        self
+    }
+
+    pub fn read(mut self, timeout: Duration) -> Self {
+        self.read = Some(timeout);
+
+        self
+    }
+
+    pub fn no_read_timeout(mut self) -> Self {
+        self.read = None;
+
+        self
+    }
+
+    pub fn deadline(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(timeout);
+
+        self
+    }
+
+    pub fn idle(mut self, timeout: Duration) -> Self {
+        self.idle = timeout;
+
+        self
+    }
+
+    pub fn expect_continue(mut self, timeout: Duration) -> Self {
+        self.expect_continue = timeout;
+
+        self
+    }
+
+    pub(crate) fn open(&self, authority: &str) -> Result<TcpStream> {
+        let stream = match self.connect {
+            Some(timeout) => {
+                let started = Instant::now();
+                let mut error = None;
+                let mut stream = None;
+                for addr in resolve(authority, timeout)? {
+                    let remaining = timeout.saturating_sub(started.elapsed());
+                    if remaining.is_zero() {
+                        return Err(Error::timeout("connect timed out", TimeoutKind::Connect));
+                    }
+                    match TcpStream::connect_timeout(&addr, remaining) {
+                        Ok(s) => {
+                            stream = Some(s);
+                            break;
+                        }
+                        Err(e) => error = Some(e),
+                    }
+                }
+                match (stream, error) {
+                    (Some(stream), _) => stream,
+                    (None, Some(e)) => return Err(Error::io(e, TimeoutKind::Connect)),
+                    (None, None) => {
+                        return Err(Error::connection(
+                            "could not resolve host",
+                            authority.to_string().some_box(),
+                        ))
+                    }
+                }
+            }
+            None => TcpStream::connect(authority).timeout_as(TimeoutKind::Connect)?,
+        };
+        stream.set_read_timeout(self.read)?;
+
+        Ok(stream)
+    }
+}
+
+fn resolve(authority: &str, timeout: Duration) -> Result<Vec<SocketAddr>> {
+    let (tx, rx) = channel();
+    let host = authority.to_string();
+    thread::spawn(move || {
+        let _ = tx.send(host.to_socket_addrs().map(|addrs| addrs.collect()));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(addrs) => addrs.timeout_as(TimeoutKind::Connect),
+        Err(_) => Err(Error::timeout("connect timed out", TimeoutKind::Connect)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct ReceiveWindows {
+    pub(crate) connection: u32,
+    pub(crate) stream: u32,
+}
+
+impl Default for ReceiveWindows {
+    fn default() -> Self {
+        Self {
+            connection: 16 * DEFAULT_WINDOW_SIZE,
+            stream: DEFAULT_WINDOW_SIZE,
+        }
+    }
+}
+
+impl ReceiveWindows {
+    fn connection(mut self, size: u32) -> Self {
+        self.connection = size.clamp(DEFAULT_WINDOW_SIZE, MAX_WINDOW_SIZE);
+
+        self
+    }
+
+    fn stream(mut self, size: u32) -> Self {
+        self.stream = size.clamp(1, MAX_WINDOW_SIZE);
+
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Http2Settings {
+    pub(crate) header_table_size: u32,
+    pub(crate) max_frame_size: u32,
+    pub(crate) max_header_list_size: Option<u32>,
+    pub(crate) windows: ReceiveWindows,
+    pub(crate) settings_timeout: Duration,
+}
+
+impl Default for Http2Settings {
+    fn default() -> Self {
+        Self {
+            header_table_size: 4096,
+            max_frame_size: MIN_FRAME_SIZE,
+            max_header_list_size: None,
+            windows: ReceiveWindows::default(),
+            settings_timeout: SETTINGS_TIMEOUT,
+        }
+    }
+}
+
+impl Http2Settings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn header_table_size(mut self, size: u32) -> Self {
+        self.header_table_size = size;
+
+        self
+    }
+
+    pub fn initial_window_size(mut self, size: u32) -> Self {
+        self.windows = self.windows.stream(size);
+
+        self
+    }
+
+    pub fn connection_window_size(mut self, size: u32) -> Self {
+        self.windows = self.windows.connection(size);
+
+        self
+    }
+
+    pub fn max_frame_size(mut self, size: u32) -> Self {
+        self.max_frame_size = size.clamp(MIN_FRAME_SIZE, MAX_FRAME_SIZE);
+
+        self
+    }
+
+    pub fn max_header_list_size(mut self, size: u32) -> Self {
+        self.max_header_list_size = Some(size);
+
+        self
+    }
+
+    pub fn settings_timeout(mut self, timeout: Duration) -> Self {
+        self.settings_timeout = timeout;
+
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConnOptions {
+    pub(crate) tls: TlsOptions,
+    pub(crate) proxy: Option<Proxy>,
+    pub(crate) env_proxy: bool,
+    pub(crate) timeouts: Timeouts,
+    pub(crate) unix_socket: Option<PathBuf>,
+    pub(crate) connector: Option<SharedConnector>,
+    pub(crate) request_compression: Option<BodyCompression>,
+    pub(crate) informational: Option<InformationalHandler>,
+    pub(crate) pipeline_depth: usize,
+    pub(crate) http2: Http2Settings,
+    #[cfg(feature = "http2")]
+    pub(crate) push_filter: Option<PushFilter>,
+}
+
+#[derive(Clone)]
+pub(crate) struct InformationalHandler(pub(crate) Arc<dyn Fn(&Response) + Send + Sync>);
+
+impl Debug for InformationalHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InformationalHandler")
+    }
+}
+
+#[cfg(feature = "http2")]
+#[derive(Clone)]
+pub(crate) struct PushFilter(pub(crate) Arc<dyn Fn(&HeaderMap) -> bool + Send + Sync>);
+
+#[cfg(feature = "http2")]
+impl Debug for PushFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PushFilter")
+    }
+}
+
+impl ConnOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = tls;
+
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+
+        self
+    }
+
+    pub fn connector<C: Connector + 'static>(mut self, connector: C) -> Self {
+        self.connector = Some(SharedConnector(Arc::new(connector)));
+
+        self
+    }
+
+    pub fn unix_socket<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.unix_socket = Some(path.into());
+
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+
+        self
+    }
+
+    pub fn proxy_from_env(mut self) -> Self {
+        self.env_proxy = true;
+
+        self
+    }
+
+    pub fn compress_requests(mut self, compression: BodyCompression) -> Self {
+        self.request_compression = Some(compression);
+
+        self
+    }
+
+    pub fn pipeline(mut self, depth: usize) -> Self {
+        self.pipeline_depth = depth;
+
+        self
+    }
+
+    pub fn http2_settings(mut self, settings: Http2Settings) -> Self {
+        self.http2 = settings;
+
+        self
+    }
+
+    pub fn on_informational<F: Fn(&Response) + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.informational = Some(InformationalHandler(Arc::new(handler)));
+
+        self
+    }
+
+    #[cfg(feature = "http2")]
+    pub fn on_push<F: Fn(&HeaderMap) -> bool + Send + Sync + 'static>(mut self, filter: F) -> Self {
+        self.push_filter = Some(PushFilter(Arc::new(filter)));
+
+        self
+    }
+
+    pub(crate) fn proxy_for(&self, url: &Url) -> Option<Proxy> {
+        match &self.proxy {
+            Some(proxy) => proxy.applies_to(url).then(|| proxy.clone()),
+            None if self.env_proxy => Proxy::from_env(url),
+            None => None,
+        }
+    }
+}
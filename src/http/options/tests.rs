@@ -0,0 +1,213 @@
+use crate::http::error::{ErrorKind, TimeoutKind};
+use crate::http::options::{ConnOptions, Timeouts};
+use crate::http::pooled_conn::PooledConn;
+use crate::http::request::RequestBuilder;
+use crate::http::test_utils::read_request_head;
+use crate::http::{HttpClient, Protocol};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const OK: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+
+fn server(replies: Vec<Option<&'static [u8]>>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for reply in replies {
+            let (mut stream, _) = listener.accept().unwrap();
+            thread::spawn(move || {
+                read_request_head(&mut stream);
+                match reply {
+                    Some(reply) => stream.write_all(reply).unwrap(),
+                    None => thread::sleep(Duration::from_millis(500)),
+                }
+            });
+        }
+    });
+
+    port
+}
+
+fn timeout_kind(kind: ErrorKind) -> TimeoutKind {
+    match kind {
+        ErrorKind::Timeout(kind, _) => kind,
+        kind => panic!("expected timeout, got {kind:?}"),
+    }
+}
+
+fn get(port: u16) -> RequestBuilder {
+    RequestBuilder::get(&format!("http://127.0.0.1:{port}/")).protocol(Protocol::HTTP1)
+}
+
+#[test]
+fn read_timeout() {
+    let port = server(vec![None]);
+    let mut client = HttpClient::new().timeouts(Timeouts::new().read(Duration::from_millis(50)));
+    let error = client.execute(get(port)).unwrap_err();
+    assert_eq!(timeout_kind(error.kind), TimeoutKind::Read);
+}
+
+#[test]
+fn deadline() {
+    let port = server(vec![None, Some(OK)]);
+    let mut client = HttpClient::new().timeouts(
+        Timeouts::new()
+            .no_read_timeout()
+            .deadline(Duration::from_millis(50)),
+    );
+    let error = client.execute(get(port)).unwrap_err();
+    assert_eq!(timeout_kind(error.kind), TimeoutKind::Deadline);
+    assert_eq!(client.execute(get(port)).unwrap().body, b"ok");
+}
+
+#[test]
+fn idle_timeout() {
+    let port = server(vec![Some(OK), Some(OK), Some(OK)]);
+    let options = ConnOptions::new().timeouts(Timeouts::new().idle(Duration::from_millis(20)));
+    let url = get(port).url;
+    let mut conn = PooledConn::new(&url, Protocol::HTTP1, &options).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    let error = conn.send_request(get(port)).unwrap_err();
+    assert_eq!(timeout_kind(error.kind), TimeoutKind::Idle);
+
+    let mut client = HttpClient::with_options(options);
+    client.connect_proto(&url, Protocol::HTTP1).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(client.execute(get(port)).unwrap().status_code, 200);
+}
+
+#[test]
+fn connect_timeout() {
+    let port = server(vec![Some(OK)]);
+    let mut client =
+        HttpClient::new().timeouts(Timeouts::new().connect(Duration::from_millis(500)));
+    assert_eq!(client.execute(get(port)).unwrap().body, b"ok");
+    let error = Timeouts::new()
+        .connect(Duration::from_millis(50))
+        .open("invalid.host.test:80")
+        .unwrap_err();
+    assert!(matches!(error.kind, ErrorKind::Connection(_)));
+}
+
+#[test]
+fn stalled_tls_handshake_is_a_connect_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        stream
+    });
+    let mut client = HttpClient::new().timeouts(Timeouts::new().read(Duration::from_millis(50)));
+    let error = client
+        .execute(
+            RequestBuilder::get(&format!("https://localhost:{port}/")).protocol(Protocol::HTTP1),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        error.kind,
+        ErrorKind::Timeout(TimeoutKind::Connect, Some(_))
+    ));
+    handle.join().unwrap();
+}
+
+#[test]
+fn deadline_covers_whole_batch() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request_head(&mut stream);
+        stream.write_all(OK).unwrap();
+        thread::sleep(Duration::from_millis(500));
+    });
+    let mut client = HttpClient::new().timeouts(
+        Timeouts::new()
+            .no_read_timeout()
+            .deadline(Duration::from_millis(100)),
+    );
+    let started = Instant::now();
+    let mut responses = client.execute_batch(vec![get(port), get(port)]).unwrap();
+    assert!(started.elapsed() < Duration::from_millis(400));
+    let error = responses.pop().unwrap().unwrap_err();
+    assert_eq!(timeout_kind(error.kind), TimeoutKind::Deadline);
+}
+
+#[test]
+fn expect_continue_wait_is_bounded() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request_head(&mut stream);
+        let mut body = [0; 7];
+        stream.read_exact(&mut body).unwrap();
+        assert_eq!(&body, b"payload");
+        stream.write_all(OK).unwrap();
+    });
+    let mut client = HttpClient::new().timeouts(
+        Timeouts::new()
+            .no_read_timeout()
+            .expect_continue(Duration::from_millis(100))
+            .deadline(Duration::from_secs(2)),
+    );
+    let started = Instant::now();
+    let response = client
+        .execute(
+            RequestBuilder::post(&format!("http://127.0.0.1:{port}/"))
+                .protocol(Protocol::HTTP1)
+                .body(b"payload")
+                .expect_continue(),
+        )
+        .unwrap();
+    assert_eq!(response.body, b"ok");
+    assert!(started.elapsed() >= Duration::from_millis(100));
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn http2_settings_keep_push() {
+    use crate::http::options::Http2Settings;
+    let options = ConnOptions::new()
+        .on_push(|_| true)
+        .http2_settings(Http2Settings::new().max_frame_size(32_768));
+    assert!(options.push_filter.is_some());
+    assert_eq!(options.http2.max_frame_size, 32_768);
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn deadline_covers_concurrent_batch() {
+    use crate::http::test_utils::{http2_handshake, http2_response, read_header_frames};
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        http2_handshake(&mut stream, Vec::new());
+        read_header_frames(&mut stream, 2);
+        stream.write_all(&http2_response(1, b"ok")).unwrap();
+        thread::sleep(Duration::from_millis(500));
+    });
+    let mut client = HttpClient::new().timeouts(
+        Timeouts::new()
+            .no_read_timeout()
+            .deadline(Duration::from_millis(100)),
+    );
+    let request =
+        || RequestBuilder::get(&format!("http://127.0.0.1:{port}/")).protocol(Protocol::HTTP2);
+    let started = Instant::now();
+    let mut completions = client.send_concurrent(vec![request(), request()]).unwrap();
+    let (index, response) = completions.next().unwrap();
+    assert_eq!((index, response.unwrap().body), (0, b"ok".to_vec()));
+    let (index, response) = completions.next().unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(
+        timeout_kind(response.unwrap_err().kind),
+        TimeoutKind::Deadline
+    );
+    assert!(completions.next().is_none());
+    assert!(started.elapsed() < Duration::from_millis(400));
+}
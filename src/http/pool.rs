@@ -1,30 +1,41 @@
 use super::{pooled_conn::PooledConn, Error, Response, Result, Success};
-use crate::http::error::{ErrorKind, SomeError};
+use crate::http::error::{ErrorKind, SomeError, TimeoutKind};
+use crate::http::options::ConnOptions;
 use crate::http::request::RequestBuilder;
-use crate::http::utf8_utils::UTF8Utils;
+use crate::http::url::Url;
 use crate::http::Protocol;
 use std::collections::HashMap;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 pub struct HostPool {
     inner: Option<JoinHandle<()>>,
     request_tx: Sender<RequestBuilder>,
     response_rx: Receiver<Result<Response>>,
+    deadline: Option<Duration>,
 }
 
 impl HostPool {
     pub fn new() -> Self {
-        let (pool, request_tx, response_rx) = Self::spawn_pool();
+        Self::with_options(ConnOptions::default())
+    }
+
+    pub fn with_options(options: ConnOptions) -> Self {
+        let deadline = options.timeouts.deadline;
+        let (pool, request_tx, response_rx) = Self::spawn_pool(options);
         Self {
             inner: Some(pool),
             request_tx,
             response_rx,
+            deadline,
         }
     }
 
-    pub fn spawn_pool() -> (
+    pub fn spawn_pool(
+        options: ConnOptions,
+    ) -> (
         JoinHandle<()>,
         Sender<RequestBuilder>,
         Receiver<Result<Response>>,
@@ -32,11 +43,11 @@ impl HostPool {
         let (request_tx, request_rx) = channel();
         let (response_tx, response_rx) = channel();
         let thread = thread::spawn(move || {
-            let mut pool = Pool::new(response_tx);
+            let mut pool = Pool::new(response_tx, options);
             let request_rx = request_rx;
             loop {
                 let request: RequestBuilder = request_rx.recv().unwrap();
-                let host = request.url.host.as_utf8().unwrap();
+                let host = request.url.origin();
                 let connection = pool.host(&host);
                 let connection = match connection {
                     Ok(i) => i,
@@ -80,11 +91,16 @@ impl HostPool {
     }
 
     pub fn fetch_response(&mut self) -> Result<Response> {
-        match self
-            .response_rx
-            .recv_timeout(std::time::Duration::from_secs(2))
-        {
+        let received = match self.deadline {
+            Some(deadline) => self.response_rx.recv_timeout(deadline),
+            None => self.response_rx.recv().map_err(RecvTimeoutError::from),
+        };
+        match received {
             Ok(i) => i,
+            Err(RecvTimeoutError::Timeout) => Err(Error::timeout(
+                "request deadline elapsed",
+                TimeoutKind::Deadline,
+            )),
             Err(e) => Err(Error::new(
                 "could not fetch response",
                 ErrorKind::Thread(e.to_string().some_box()),
@@ -95,30 +111,34 @@ impl HostPool {
 
 pub struct Pool {
     map: HashMap<String, PooledConn>,
+    options: ConnOptions,
     pub response_tx: Sender<Result<Response>>,
 }
 
 impl Pool {
-    pub fn new(response_tx: Sender<Result<Response>>) -> Self {
+    pub fn new(response_tx: Sender<Result<Response>>, options: ConnOptions) -> Self {
         Self {
             map: HashMap::with_capacity(4),
+            options,
             response_tx,
         }
     }
-    pub fn spawn_connection(addr: &str) -> Result<PooledConn> {
-        PooledConn::new(addr, Protocol::default())
+    pub fn spawn_connection(&self, url: &Url) -> Result<PooledConn> {
+        PooledConn::new(url, Protocol::default(), &self.options)
     }
 
-    pub fn host(&mut self, addr: &str) -> Result<&mut PooledConn> {
-        let connection = self
-            .map
-            .entry(addr.to_string())
-            .or_insert(Self::spawn_connection(&addr)?);
+    pub fn host(&mut self, url: &Url) -> Result<&mut PooledConn> {
+        let addr = url.authority();
+        if !self.map.get_mut(&addr).is_some_and(|c| c.is_active()) {
+            let connection = self.spawn_connection(url)?;
+            self.map.insert(addr.clone(), connection);
+        }
+        let connection = self.map.get_mut(&addr).unwrap();
         match connection.is_active() {
             true => Ok(connection),
             false => Err(Error::new(
                 "host connection lost",
-                ErrorKind::Thread(addr.to_string().some_box()),
+                ErrorKind::Thread(addr.some_box()),
             )),
         }
     }
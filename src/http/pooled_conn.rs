@@ -1,44 +1,98 @@
 use super::proto_conn::ProtoConn;
 use super::{Error, ErrorKind, Response, Result, Success};
-use crate::http::error::SomeError;
+use crate::http::body::BodySender;
+use crate::http::error::{SomeError, TimeoutKind};
+use crate::http::options::ConnOptions;
 use crate::http::request::RequestBuilder;
+use crate::http::transport::ShutdownHandle;
+use crate::http::url::Url;
 use crate::http::Protocol;
 #[cfg(feature = "http2")]
 use crate::http::Protocol::{HTTP1, HTTP2};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::fmt::{Debug, Formatter};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub enum Job {
+    Request(Box<RequestBuilder>, Option<BodySender>),
+    Batch(Vec<RequestBuilder>),
+    Multiplex(Vec<RequestBuilder>, Sender<(usize, Result<Response>)>),
+    CancelPush(Url),
+}
+
+impl Job {
+    #[cfg(feature = "http2")]
+    fn protocol(&self) -> Option<Protocol> {
+        match self {
+            Self::Request(request, _) => Some(request.protocol),
+            Self::Batch(requests) | Self::Multiplex(requests, _) => {
+                requests.first().map(|request| request.protocol)
+            }
+            Self::CancelPush(_) => None,
+        }
+    }
+}
+
+pub(crate) struct AbortHandle {
+    status: Arc<Mutex<ConnectionStatus>>,
+    socket: Arc<Mutex<Option<ShutdownHandle>>>,
+}
+
+impl AbortHandle {
+    pub(crate) fn abort(&self) {
+        *self.status.lock().unwrap() = ConnectionStatus::DEAD;
+        if let Some(shutdown) = self.socket.lock().unwrap().take() {
+            let _ = shutdown();
+        }
+    }
+}
 
-#[derive(Debug)]
 pub struct PooledConn {
     pub host: String,
-    pub request_tx: Sender<RequestBuilder>,
+    pub request_tx: Sender<Job>,
     pub response_rx: Receiver<Result<Response>>,
     pub status: Arc<Mutex<ConnectionStatus>>,
     thread: Option<JoinHandle<ProtoConn>>,
+    socket: Arc<Mutex<Option<ShutdownHandle>>>,
+    deadline: Option<Duration>,
+    sent_at: Option<Instant>,
+    awaiting: usize,
+    aborted: bool,
+}
+
+impl Debug for PooledConn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledConn")
+            .field("host", &self.host)
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
 }
 
 impl PooledConn {
-    pub fn new(authority: &str, protocol: Protocol) -> Result<Self> {
-        let timeout = std::time::Duration::from_secs(30);
-        let (request_tx, request_rx): (Sender<RequestBuilder>, Receiver<RequestBuilder>) =
-            channel();
+    pub fn new(url: &Url, protocol: Protocol, options: &ConnOptions) -> Result<Self> {
+        let timeout = options.timeouts.idle;
+        let (request_tx, request_rx): (Sender<Job>, Receiver<Job>) = channel();
         let (response_tx, response_rx) = channel();
-        let conn = ProtoConn::new(authority, protocol)?;
+        let conn = ProtoConn::new(url, protocol, options)?;
         let status = Arc::new(Mutex::new(ConnectionStatus::ACTIVE));
-        conn.inner
-            .sock
-            .set_read_timeout(Some(std::time::Duration::from_secs(3)))
-            .unwrap();
+        let socket = Arc::new(Mutex::new(conn.shutdown_handle()));
         let thread = Self::spawn_thread(conn, status.clone(), timeout, request_rx, response_tx);
 
         Ok(Self {
-            host: authority.to_string(),
+            host: url.authority(),
             request_tx,
             response_rx,
             thread: Some(thread),
             status,
+            socket,
+            deadline: options.timeouts.deadline,
+            sent_at: None,
+            awaiting: 0,
+            aborted: false,
         })
     }
 
@@ -46,7 +100,7 @@ impl PooledConn {
         conn: ProtoConn,
         status: Arc<Mutex<ConnectionStatus>>,
         timeout: std::time::Duration,
-        request_rx: Receiver<RequestBuilder>,
+        request_rx: Receiver<Job>,
         response_tx: Sender<Result<Response>>,
     ) -> JoinHandle<ProtoConn> {
         thread::spawn(move || {
@@ -54,28 +108,91 @@ impl PooledConn {
             let response_tx = response_tx;
             let mut connection = conn;
             'inner: loop {
-                let request = match request_rx.recv_timeout(timeout) {
+                let idle = connection
+                    .keep_alive
+                    .map_or(timeout, |keep_alive| keep_alive.min(timeout));
+                let job = match request_rx.recv_timeout(idle) {
                     Ok(i) => i,
-                    Err(_) => {
+                    Err(e) => {
                         let mut s = status.lock().unwrap();
-                        *s = ConnectionStatus::DEAD;
+                        *s = match e {
+                            RecvTimeoutError::Timeout => ConnectionStatus::IDLE,
+                            RecvTimeoutError::Disconnected => ConnectionStatus::DEAD,
+                        };
                         break 'inner;
                     }
                 };
                 #[cfg(feature = "http2")]
-                if request.protocol == HTTP1 && connection.codec.kind() == HTTP2 {
+                if job.protocol() == Some(HTTP1) && connection.codec.kind() == HTTP2 {
                     if let Err(_) = connection.downgrade_protocol() {
                         let mut s = status.lock().unwrap();
                         *s = ConnectionStatus::DEAD;
                         break 'inner;
                     }
                 }
-                let response = connection.send_request(request);
+                let (request, body_tx) = match job {
+                    Job::Request(request, body_tx) => (*request, body_tx),
+                    Job::Batch(requests) => {
+                        for response in connection.pipeline(requests) {
+                            if response_tx.send(response).is_err() {
+                                let mut s = status.lock().unwrap();
+                                *s = ConnectionStatus::DEAD;
+                                break 'inner;
+                            }
+                        }
+                        if connection.closing {
+                            let mut s = status.lock().unwrap();
+                            *s = ConnectionStatus::DEAD;
+                            break 'inner;
+                        }
+                        continue 'inner;
+                    }
+                    Job::Multiplex(requests, completion_tx) => {
+                        connection.multiplex(requests, &mut |index, response| {
+                            let _ = completion_tx.send((index, response));
+                        });
+                        if connection.closing {
+                            let mut s = status.lock().unwrap();
+                            *s = ConnectionStatus::DEAD;
+                            break 'inner;
+                        }
+                        continue 'inner;
+                    }
+                    Job::CancelPush(url) => {
+                        if connection.cancel_push(&url).is_err() {
+                            let mut s = status.lock().unwrap();
+                            *s = ConnectionStatus::DEAD;
+                            break 'inner;
+                        }
+                        continue 'inner;
+                    }
+                };
+                let response = connection.exchange(request, body_tx.is_some());
+                let streaming = response.is_ok();
+                if body_tx.is_some() && streaming {
+                    *status.lock().unwrap() = ConnectionStatus::STREAMING;
+                }
                 if let Err(_) = response_tx.send(response) {
                     let mut s = status.lock().unwrap();
                     *s = ConnectionStatus::DEAD;
                     break 'inner;
                 }
+                if let (Some(body_tx), true) = (body_tx, streaming) {
+                    if !connection.stream_body(&body_tx) {
+                        let mut s = status.lock().unwrap();
+                        *s = ConnectionStatus::DEAD;
+                        break 'inner;
+                    }
+                    let mut s = status.lock().unwrap();
+                    if *s == ConnectionStatus::STREAMING {
+                        *s = ConnectionStatus::ACTIVE;
+                    }
+                }
+                if connection.closing {
+                    let mut s = status.lock().unwrap();
+                    *s = ConnectionStatus::DEAD;
+                    break 'inner;
+                }
             }
 
             connection
@@ -83,25 +200,42 @@ impl PooledConn {
     }
 
     pub fn spawn_connection(&mut self, conn: ProtoConn) {
-        let timeout = std::time::Duration::from_secs(30);
-        let (request_tx, request_rx): (Sender<RequestBuilder>, Receiver<RequestBuilder>) =
-            channel();
+        let timeout = conn.options.timeouts.idle;
+        let (request_tx, request_rx): (Sender<Job>, Receiver<Job>) = channel();
         let (response_tx, response_rx) = channel();
         let status = Arc::new(Mutex::new(ConnectionStatus::ACTIVE));
-        conn.inner
-            .sock
-            .set_read_timeout(Some(std::time::Duration::from_secs(3)))
-            .unwrap();
+        self.socket = Arc::new(Mutex::new(conn.shutdown_handle()));
+        self.deadline = conn.options.timeouts.deadline;
         let thread = Self::spawn_thread(conn, status.clone(), timeout, request_rx, response_tx);
         self.request_tx = request_tx;
         self.response_rx = response_rx;
         self.status = status;
         self.thread = Some(thread);
+        self.aborted = false;
     }
 
     pub fn check_response(&mut self) -> Result<Response> {
-        match self.response_rx.recv() {
+        let sent_at = match self.awaiting {
+            0 | 1 => self.sent_at.take(),
+            _ => self.sent_at,
+        };
+        self.awaiting = self.awaiting.saturating_sub(1);
+        let received = match (self.deadline, sent_at) {
+            _ if self.aborted => Err(RecvTimeoutError::Timeout),
+            (Some(deadline), Some(sent_at)) => self
+                .response_rx
+                .recv_timeout(deadline.saturating_sub(sent_at.elapsed())),
+            _ => self.response_rx.recv().map_err(RecvTimeoutError::from),
+        };
+        match received {
             Ok(i) => i,
+            Err(RecvTimeoutError::Timeout) => {
+                self.abort();
+                Err(Error::timeout(
+                    "request deadline elapsed",
+                    TimeoutKind::Deadline,
+                ))
+            }
             Err(e) => Err(Error::new(
                 "could not retrieve request",
                 ErrorKind::Thread(Some(Box::new(e.to_string()))),
@@ -109,14 +243,65 @@ impl PooledConn {
         }
     }
 
+    fn abort(&mut self) {
+        self.aborted = true;
+        self.abort_handle().abort();
+    }
+
+    pub(crate) fn abort_handle(&self) -> AbortHandle {
+        AbortHandle {
+            status: self.status.clone(),
+            socket: self.socket.clone(),
+        }
+    }
+
     pub fn send_request(&mut self, request: RequestBuilder) -> Success {
-        self.request_tx.send(request).map_err(|e| {
+        self.dispatch(Job::Request(Box::new(request), None), 1)
+    }
+
+    pub fn send_streaming(&mut self, request: RequestBuilder, body_tx: BodySender) -> Success {
+        self.dispatch(Job::Request(Box::new(request), Some(body_tx)), 1)
+    }
+
+    pub fn send_batch(&mut self, requests: Vec<RequestBuilder>) -> Success {
+        let count = requests.len();
+        self.dispatch(Job::Batch(requests), count)
+    }
+
+    pub fn send_multiplexed(
+        &mut self,
+        requests: Vec<RequestBuilder>,
+        completion_tx: Sender<(usize, Result<Response>)>,
+    ) -> Success {
+        self.dispatch(Job::Multiplex(requests, completion_tx), 0)
+    }
+
+    pub fn cancel_push(&mut self, url: Url) -> Success {
+        self.dispatch(Job::CancelPush(url), 0)
+    }
+
+    fn dispatch(&mut self, job: Job, responses: usize) -> Success {
+        if self.is_idle() {
+            return Err(Error::timeout(
+                "connection idle timeout elapsed",
+                TimeoutKind::Idle,
+            ));
+        }
+        if self.is_streaming() {
+            return Err(Error::user("connection is still streaming a response body"));
+        }
+        if responses > 0 {
+            self.sent_at = Some(Instant::now());
+            self.awaiting = responses;
+        }
+        self.request_tx.send(job).map_err(|e| {
             Error::new(
                 "could not send request",
                 ErrorKind::Thread(Some(Box::new(e.to_string()))),
             )
         })
     }
+
     pub fn is_active(&mut self) -> bool {
         let status = self.status.lock().unwrap();
         *status == ConnectionStatus::ACTIVE
@@ -127,6 +312,16 @@ impl PooledConn {
         *status == ConnectionStatus::DEAD
     }
 
+    pub fn is_idle(&mut self) -> bool {
+        let status = self.status.lock().unwrap();
+        *status == ConnectionStatus::IDLE
+    }
+
+    pub fn is_streaming(&mut self) -> bool {
+        let status = self.status.lock().unwrap();
+        *status == ConnectionStatus::STREAMING
+    }
+
     pub fn join_thread(&mut self) -> Result<Option<ProtoConn>> {
         let thread = self.thread.take();
         match thread {
@@ -140,7 +335,7 @@ impl PooledConn {
     #[cfg(feature = "http2")]
     pub fn ping_inner(&mut self) -> Success {
         let request = RequestBuilder::connect("ping");
-        
+
         self.send_request(request)
     }
 }
@@ -148,5 +343,7 @@ impl PooledConn {
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum ConnectionStatus {
     ACTIVE,
+    STREAMING,
+    IDLE,
     DEAD,
 }
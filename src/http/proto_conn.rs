@@ -1,78 +1,162 @@
-use super::{error::SomeError, Error, Result};
+use super::{error::SomeError, Error, ErrorKind, Result};
+use crate::http::body::BodySender;
 use crate::http::codec::Codec;
+use crate::http::encoding;
+use crate::http::error::{IoTimeout, TimeoutKind};
 use crate::http::http1::codec::Http1Codec;
 #[cfg(feature = "http2")]
 use crate::http::http2::codec::Http2Codec;
+use crate::http::options::ConnOptions;
+use crate::http::proxy::Proxy;
 use crate::http::request::RequestBuilder;
+use crate::http::status;
+use crate::http::tls::{self, TlsOptions};
+use crate::http::transport::{ShutdownHandle, Transport};
+use crate::http::url::Url;
+use crate::http::utf8_utils::UTF8Utils;
 use crate::http::{Protocol, Response, Success};
 use rustls::client::InvalidDnsNameError;
 use rustls::ClientConnection as TlsClient;
 use rustls::StreamOwned as TlsStream;
-use std::io::Write;
-use std::net::TcpStream;
+use std::collections::VecDeque;
+use std::io::{Cursor, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-
-pub(crate) type Inner = TlsStream<TlsClient, TcpStream>;
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "http2")]
 pub const H2: &[u8] = b"h2";
 pub const H1: &[u8] = b"http/1.1";
 #[cfg(feature = "http2")]
 pub const ALPN: &[&[u8]] = &[H2, H1];
+const STALE_CHECK_AFTER: Duration = Duration::from_secs(1);
 
 pub struct ProtoConn {
-    pub(crate) inner: Inner,
+    pub(crate) transport: Box<dyn Transport>,
     pub(crate) codec: Box<dyn Codec>,
-    pub(crate) authority: String,
+    pub(crate) origin: Url,
+    pub(crate) options: ConnOptions,
+    pub(crate) closing: bool,
+    pub(crate) keep_alive: Option<Duration>,
+    last_used: Instant,
+    shut_down: Arc<AtomicBool>,
 }
 
 impl ProtoConn {
-    pub fn new(authority: &str, protocol: Protocol) -> Result<Self> {
+    pub fn new(url: &Url, protocol: Protocol, options: &ConnOptions) -> Result<Self> {
+        let (transport, forward) = match (&options.connector, options.unix_socket.clone()) {
+            (Some(connector), _) => (connector.0.connect(url, options)?, None),
+            (None, Some(path)) => (Self::connect_unix(&path, options)?, None),
+            (None, None) => match url.unix_path() {
+                Some(path) => (Self::connect_unix(&path, options)?, None),
+                None => Self::connect_tcp(url, options)?,
+            },
+        };
+
+        Self::assemble(transport, url, protocol, options, forward)
+    }
+
+    pub fn with_transport(
+        transport: Box<dyn Transport>,
+        url: &Url,
+        protocol: Protocol,
+        options: &ConnOptions,
+    ) -> Result<Self> {
+        Self::assemble(transport, url, protocol, options, None)
+    }
+
+    fn assemble(
+        transport: Box<dyn Transport>,
+        url: &Url,
+        protocol: Protocol,
+        options: &ConnOptions,
+        forward: Option<Proxy>,
+    ) -> Result<Self> {
+        let transport = match url.is_secure() && !transport.is_secure() {
+            true => Self::secure(transport, url, protocol, &options.tls)?,
+            false => transport,
+        };
+        let codec: Box<dyn Codec> = match (protocol, forward) {
+            (_, Some(proxy)) => Box::new(Http1Codec::forwarding(proxy.authorization())),
+            (Protocol::HTTP1, None) => Box::new(Http1Codec::new()),
+            #[cfg(feature = "http2")]
+            (Protocol::HTTP2, None) => Box::new(Http2Codec::new(
+                options.http2,
+                options.push_filter.clone(),
+                options.timeouts.read,
+            )),
+        };
+        let mut conn = Self {
+            transport,
+            codec,
+            origin: url.origin(),
+            options: options.clone(),
+            closing: false,
+            keep_alive: None,
+            last_used: Instant::now(),
+            shut_down: Arc::new(AtomicBool::new(false)),
+        };
+        conn.codec.prelude(conn.transport.as_mut())?;
+
+        Ok(conn)
+    }
+
+    fn connect_tcp(
+        url: &Url,
+        options: &ConnOptions,
+    ) -> Result<(Box<dyn Transport>, Option<Proxy>)> {
+        match options.proxy_for(url) {
+            Some(proxy) => {
+                let stream = proxy.tunnel(url, &options.timeouts)?;
+                let forward = proxy.forwards(url).then_some(proxy);
+
+                Ok((Box::new(stream), forward))
+            }
+            None => Ok((Box::new(options.timeouts.open(&url.authority())?), None)),
+        }
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(path: &Path, options: &ConnOptions) -> Result<Box<dyn Transport>> {
+        let stream = UnixStream::connect(path).timeout_as(TimeoutKind::Connect)?;
+        stream.set_read_timeout(options.timeouts.read)?;
+
+        Ok(Box::new(stream))
+    }
+
+    #[cfg(not(unix))]
+    fn connect_unix(_path: &Path, _options: &ConnOptions) -> Result<Box<dyn Transport>> {
+        Err(Error::user(
+            "unix sockets are not supported on this platform",
+        ))
+    }
+
+    fn secure(
+        transport: Box<dyn Transport>,
+        url: &Url,
+        protocol: Protocol,
+        tls: &TlsOptions,
+    ) -> Result<Box<dyn Transport>> {
         let alpn = match protocol {
             Protocol::HTTP1 => &[H1],
             #[cfg(feature = "http2")]
             Protocol::HTTP2 => ALPN,
         };
-        let stream = TcpStream::connect(authority)?;
-        let tls_client = Self::config_tls(
-            authority.trim_end_matches(|c: char| c == ':' || c.is_numeric()),
-            alpn,
-        )?;
-        let mut conn = match protocol {
-            Protocol::HTTP1 => Self {
-                inner: TlsStream::new(tls_client, stream),
-                codec: Box::new(Http1Codec::new()),
-                authority: authority.to_string(),
-            },
-            #[cfg(feature = "http2")]
-            Protocol::HTTP2 => Self {
-                inner: TlsStream::new(tls_client, stream),
-                codec: Box::new(Http2Codec::new()),
-                authority: authority.to_string(),
-            },
-        };
-        conn.codec.prelude(&mut conn.inner)?;
+        let host = url.host.as_utf8_lossy();
+        let tls_client = Self::config_tls(&host, alpn, tls)?;
+        let mut stream = TlsStream::new(tls_client, transport);
+        let TlsStream { conn, sock } = &mut stream;
+        conn.complete_io(sock)
+            .map_err(|e| tls::handshake_error(e, &host))?;
 
-        Ok(conn)
+        Ok(Box::new(stream))
     }
 
-    fn config_tls(host: &str, protocols: &[&[u8]]) -> Result<TlsClient> {
-        let mut root_store = rustls::RootCertStore::empty();
-        root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
-            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
-                ta.subject,
-                ta.spki,
-                ta.name_constraints,
-            )
-        }));
-        let mut config = rustls::ClientConfig::builder()
-            .with_safe_defaults()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
-        protocols
-            .into_iter()
-            .for_each(|p| config.alpn_protocols.push(p.to_vec()));
-        let rc_config = Arc::new(config);
+    fn config_tls(host: &str, protocols: &[&[u8]], tls: &TlsOptions) -> Result<TlsClient> {
+        let rc_config = Arc::new(tls.client_config(host, protocols)?);
 
         TlsClient::new(
             rc_config,
@@ -85,21 +169,317 @@ impl ProtoConn {
 
     #[cfg(feature = "http2")]
     pub fn downgrade_protocol(&mut self) -> Success {
-        *self = Self::new(&self.authority, Protocol::HTTP1)?;
+        *self = Self::new(&self.origin, Protocol::HTTP1, &self.options)?;
 
         Ok(())
     }
 
     pub fn reset(&mut self) -> Success {
-        *self = Self::new(&self.authority, self.codec.kind())?;
+        *self = Self::new(&self.origin, self.codec.kind(), &self.options)?;
 
         Ok(())
     }
 
+    pub fn exchange(&mut self, request: RequestBuilder, streaming: bool) -> Result<Response> {
+        if self.is_stale() {
+            self.reset()?;
+        }
+        if !streaming {
+            match self.codec.take_pushed(&request, self.transport.as_mut()) {
+                Ok(Some(response)) => return Ok(response),
+                Ok(None) => {}
+                Err(e) => {
+                    self.closing = true;
+                    return Err(e);
+                }
+            }
+        }
+        let replay = match request.method.is_idempotent() && request.body_reader.is_none() {
+            true => Some(request.clone()),
+            false => None,
+        };
+        match (self.send(request, streaming), replay) {
+            (Err(e), Some(request)) if self.is_replayable(&e) => {
+                self.reset()?;
+                self.send(request, streaming)
+            }
+            (response, _) => response,
+        }
+    }
+
+    pub fn cancel_push(&mut self, url: &Url) -> Success {
+        if self.is_stale() {
+            return Ok(());
+        }
+
+        self.codec.cancel_push(url, self.transport.as_mut())
+    }
+
+    fn send(&mut self, request: RequestBuilder, streaming: bool) -> Result<Response> {
+        match streaming {
+            true => self.send_request_head(request),
+            false => self.send_request(request),
+        }
+    }
+
+    fn is_replayable(&self, error: &Error) -> bool {
+        matches!(error.kind, ErrorKind::Connection(_))
+            && !self.codec.response_started()
+            && !self.shut_down.load(Ordering::Relaxed)
+    }
+
+    fn is_stale(&mut self) -> bool {
+        let idle = self.last_used.elapsed();
+        self.last_used = Instant::now();
+
+        self.closing || (idle >= STALE_CHECK_AFTER && self.transport.is_closed())
+    }
+
+    pub fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        let shutdown = self.transport.shutdown_handle()?;
+        let shut_down = self.shut_down.clone();
+
+        Some(Box::new(move || {
+            shut_down.store(true, Ordering::Relaxed);
+            shutdown()
+        }))
+    }
+
     pub fn send_request(&mut self, request: RequestBuilder) -> Result<Response> {
+        let head = self.send_request_head(request)?;
+        self.codec.decode_body(head, self.transport.as_mut())
+    }
+
+    pub fn send_request_head(&mut self, request: RequestBuilder) -> Result<Response> {
+        let head = match self.write_request(request)? {
+            Some(head) => head,
+            None => self.read_head()?,
+        };
+        self.observe(&head);
+
+        Ok(head)
+    }
+
+    pub fn pipeline(&mut self, requests: Vec<RequestBuilder>) -> Vec<Result<Response>> {
+        #[cfg(feature = "http2")]
+        if self.codec.kind() == Protocol::HTTP2 {
+            let mut responses: Vec<Option<Result<Response>>> =
+                requests.iter().map(|_| None).collect();
+            self.multiplex(requests, &mut |index, response| {
+                responses[index] = Some(response)
+            });
+
+            return responses
+                .into_iter()
+                .map(|response| {
+                    response.unwrap_or_else(|| Err(Error::connection("no response received", None)))
+                })
+                .collect();
+        }
+        let depth = self.options.pipeline_depth;
+        let pipelined = depth > 1
+            && self.codec.kind() == Protocol::HTTP1
+            && requests.iter().all(Self::can_pipeline);
+        let mut responses = Vec::with_capacity(requests.len());
+        let mut queue = VecDeque::from(requests);
+        while pipelined && !queue.is_empty() {
+            let window: Vec<RequestBuilder> = queue.iter().take(depth).cloned().collect();
+            let count = window.len();
+            let answered = self.pipeline_window(window, &mut responses);
+            queue.drain(..answered);
+            if answered < count {
+                break;
+            }
+        }
+        for request in queue {
+            responses.push(self.exchange(request, false));
+        }
+
+        responses
+    }
+
+    pub fn multiplex(
+        &mut self,
+        requests: Vec<RequestBuilder>,
+        complete: &mut dyn FnMut(usize, Result<Response>),
+    ) {
+        #[cfg(feature = "http2")]
+        if self.codec.kind() == Protocol::HTTP2 {
+            if self.is_stale() {
+                if let Err(e) = self.reset() {
+                    for index in 0..requests.len() {
+                        complete(index, Err(Error::connection(&e.message, None)));
+                    }
+                    return;
+                }
+            }
+            let informational = self.options.informational.clone();
+            let mut route = |index, response: Result<Response>| match response {
+                Ok(head) if status::is_informational(head.status_code) => {
+                    if let Some(handler) = &informational {
+                        (handler.0)(&head);
+                    }
+                }
+                response => complete(index, response),
+            };
+            if self
+                .codec
+                .multiplex(requests, self.transport.as_mut(), &mut route)
+                .is_err()
+            {
+                self.closing = true;
+            }
+            return;
+        }
+        for (index, response) in self.pipeline(requests).into_iter().enumerate() {
+            complete(index, response);
+        }
+    }
+
+    fn can_pipeline(request: &RequestBuilder) -> bool {
+        request.method.is_idempotent()
+            && request.method.check().is_ok()
+            && request.body_reader.is_none()
+            && !request.expects_continue()
+    }
+
+    fn pipeline_window(
+        &mut self,
+        window: Vec<RequestBuilder>,
+        responses: &mut Vec<Result<Response>>,
+    ) -> usize {
+        if self.is_stale() && self.reset().is_err() {
+            return 0;
+        }
+        let count = window.len();
+        let mut answered = 0;
+        let mut encoded = Vec::new();
+        for request in window {
+            match self.codec.encode_request(request) {
+                Ok(request) => encoded.extend(request),
+                Err(_) => return self.abandon_pipeline(answered),
+            }
+        }
+        if self.transport.write_all(&encoded).is_err() || self.transport.flush().is_err() {
+            return self.abandon_pipeline(answered);
+        }
+        while answered < count && !self.closing {
+            let response = self.read_head().and_then(|head| {
+                self.observe(&head);
+                self.codec.decode_body(head, self.transport.as_mut())
+            });
+            match response {
+                Ok(response) => responses.push(Ok(response)),
+                Err(_) => break,
+            }
+            answered += 1;
+        }
+        match answered < count {
+            true => self.abandon_pipeline(answered),
+            false => answered,
+        }
+    }
+
+    fn abandon_pipeline(&mut self, answered: usize) -> usize {
+        let _ = self.reset();
+
+        answered
+    }
+
+    fn observe(&mut self, head: &Response) {
+        if head.headers.connection_close() || self.codec.closes_connection() {
+            self.closing = true;
+        }
+        if let Some(timeout) = head.headers.keep_alive_timeout() {
+            self.keep_alive = Some(timeout);
+        }
+    }
+
+    fn write_request(&mut self, mut request: RequestBuilder) -> Result<Option<Response>> {
+        let expect_continue = request.expects_continue();
+        if expect_continue {
+            encoding::compress_body(&mut request)?;
+            if let Some(body) = request.body.take() {
+                let length = body.len() as u64;
+                request.body_reader_mut(Cursor::new(body), Some(length));
+            }
+        }
+        let body = request.body_reader.clone();
         let encoded = self.codec.encode_request(request)?;
-        self.inner.write_all(&encoded)?;
-        self.inner.flush()?;
-        self.codec.decode_response(&mut self.inner)
+        self.transport
+            .write_all(&encoded)
+            .timeout_as(TimeoutKind::Write)?;
+        if let Some(body) = body {
+            if expect_continue {
+                self.transport.flush().timeout_as(TimeoutKind::Write)?;
+                if let Some(head) = self.await_continue()? {
+                    self.closing = self.codec.kind() == Protocol::HTTP1;
+                    return Ok(Some(head));
+                }
+            }
+            self.codec.write_body(body, self.transport.as_mut())?;
+        }
+        self.transport.flush().timeout_as(TimeoutKind::Write)?;
+
+        Ok(None)
+    }
+
+    fn await_continue(&mut self) -> Result<Option<Response>> {
+        let timeouts = self.options.timeouts;
+        self.transport
+            .set_read_timeout(Some(timeouts.expect_continue))?;
+        let interim = self.read_interim();
+        self.transport.set_read_timeout(timeouts.read)?;
+        match interim {
+            Err(Error {
+                kind: ErrorKind::Timeout(TimeoutKind::Read, _),
+                ..
+            }) if !self.codec.response_started() => Ok(None),
+            interim => interim,
+        }
+    }
+
+    fn read_interim(&mut self) -> Result<Option<Response>> {
+        loop {
+            let head = self.codec.decode_head(self.transport.as_mut())?;
+            match head.status_code {
+                status::CONTINUE => return Ok(None),
+                code if status::is_informational(code) => self.informational(&head),
+                _ => return Ok(Some(head)),
+            }
+        }
+    }
+
+    fn read_head(&mut self) -> Result<Response> {
+        loop {
+            let head = self.codec.decode_head(self.transport.as_mut())?;
+            match status::is_informational(head.status_code) {
+                true => self.informational(&head),
+                false => return Ok(head),
+            }
+        }
+    }
+
+    fn informational(&self, head: &Response) {
+        if let Some(handler) = &self.options.informational {
+            (handler.0)(head);
+        }
+    }
+
+    pub fn stream_body(&mut self, body_tx: &BodySender) -> bool {
+        loop {
+            match self.codec.decode_chunk(self.transport.as_mut()) {
+                Ok(Some(chunk)) => {
+                    if body_tx.send(Ok(chunk)).is_err() {
+                        return false;
+                    }
+                }
+                Ok(None) => return true,
+                Err(e) => {
+                    let _ = body_tx.send(Err(e));
+                    return false;
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,217 @@
+use super::{error::SomeError, Error, Result, Success};
+use crate::http::error::{IoTimeout, TimeoutKind};
+use crate::http::options::Timeouts;
+use crate::http::request::headers::{HOST, PROXY_AUTHORIZATION};
+use crate::http::url::Url;
+use crate::http::utf8_utils::{COLSP, CRLF, SP};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+mod socks5;
+#[cfg(test)]
+mod tests;
+
+pub const HTTPS_PROXY: &[&str] = &["HTTPS_PROXY", "https_proxy"];
+pub const HTTP_PROXY: &[&str] = &["HTTP_PROXY", "http_proxy"];
+pub const NO_PROXY: &[&str] = &["NO_PROXY", "no_proxy"];
+const CONNECT: &[u8] = b"CONNECT ";
+const HTTP_VERSION: &[u8] = b"HTTP/1.1";
+const BASIC: &[u8] = b"Basic ";
+const HEAD_END: &[u8] = b"\r\n\r\n";
+const MAX_HEAD: usize = 8192;
+const PROXY_AUTH_REQUIRED: u16 = 407;
+const SOCKS5: &str = "socks5://";
+const SOCKS5H: &str = "socks5h://";
+const SOCKS5_SCHEMES: &[&str] = &[SOCKS5, SOCKS5H];
+const SOCKS5_PORT: u16 = 1080;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProxyKind {
+    Http,
+    Socks5,
+}
+
+#[derive(Clone, Debug)]
+pub struct Proxy {
+    pub(crate) kind: ProxyKind,
+    pub(crate) url: Url,
+    pub(crate) credentials: Option<(String, String)>,
+    pub(crate) no_proxy: Vec<String>,
+    pub(crate) remote_dns: bool,
+    pub(crate) unparsed_url: Option<String>,
+}
+
+impl Proxy {
+    pub fn http(url: &str) -> Self {
+        Self::with_url(ProxyKind::Http, url, true)
+    }
+
+    pub fn socks5(url: &str) -> Self {
+        let remote_dns = url.starts_with(SOCKS5H);
+        let url = SOCKS5_SCHEMES
+            .iter()
+            .find_map(|scheme| url.strip_prefix(scheme))
+            .unwrap_or(url);
+        let mut proxy = Self::with_url(ProxyKind::Socks5, url, remote_dns);
+        proxy.url.port.get_or_insert(SOCKS5_PORT);
+
+        proxy
+    }
+
+    fn with_url(kind: ProxyKind, url: &str, remote_dns: bool) -> Self {
+        let (mut parsed, unparsed_url) = match Url::parse(url) {
+            Ok(parsed) => (parsed, None),
+            Err(_) => (Url::default(), Some(url.to_string())),
+        };
+        let credentials = parsed.credentials();
+        parsed.userinfo.clear();
+
+        Self {
+            kind,
+            url: parsed,
+            credentials,
+            no_proxy: Vec::new(),
+            remote_dns,
+            unparsed_url,
+        }
+    }
+
+    pub fn parse(url: &str) -> Self {
+        match SOCKS5_SCHEMES.iter().any(|scheme| url.starts_with(scheme)) {
+            true => Self::socks5(url),
+            false => Self::http(url),
+        }
+    }
+
+    pub fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+
+        self
+    }
+
+    pub fn remote_dns(mut self, remote_dns: bool) -> Self {
+        self.remote_dns = remote_dns;
+
+        self
+    }
+
+    pub fn no_proxy(mut self, hosts: &str) -> Self {
+        self.no_proxy.extend(
+            hosts
+                .split(',')
+                .map(|h| h.trim().trim_start_matches('.').to_ascii_lowercase())
+                .filter(|h| !h.is_empty()),
+        );
+
+        self
+    }
+
+    pub fn from_env(url: &Url) -> Option<Self> {
+        Self::from_vars(url, |key| std::env::var(key).ok())
+    }
+
+    pub(crate) fn from_vars<F: Fn(&str) -> Option<String>>(url: &Url, var: F) -> Option<Self> {
+        let lookup = |keys: &[&str]| keys.iter().find_map(|k| var(k).filter(|v| !v.is_empty()));
+        let proxy = match url.is_secure() {
+            true => lookup(HTTPS_PROXY),
+            false => lookup(HTTP_PROXY),
+        }?;
+        let proxy = Self::parse(&proxy).no_proxy(&lookup(NO_PROXY).unwrap_or_default());
+
+        proxy.applies_to(url).then_some(proxy)
+    }
+
+    pub fn applies_to(&self, url: &Url) -> bool {
+        let host = String::from_utf8_lossy(&url.host).to_ascii_lowercase();
+        !self.no_proxy.iter().any(|pattern| {
+            pattern == "*"
+                || *pattern == host
+                || host
+                    .strip_suffix(pattern.as_str())
+                    .is_some_and(|h| h.ends_with('.'))
+        })
+    }
+
+    pub(crate) fn tunnel(&self, url: &Url, timeouts: &Timeouts) -> Result<TcpStream> {
+        Url::reparse(&self.unparsed_url)?;
+        let mut stream = timeouts.open(&self.url.authority())?;
+        match self.kind {
+            ProxyKind::Http if self.forwards(url) => {}
+            ProxyKind::Http => self.http_connect(&mut stream, url)?,
+            ProxyKind::Socks5 => {
+                socks5::connect(&mut stream, url, self.credentials.as_ref(), self.remote_dns)?
+            }
+        }
+
+        Ok(stream)
+    }
+
+    pub(crate) fn forwards(&self, url: &Url) -> bool {
+        self.kind == ProxyKind::Http && !url.is_secure()
+    }
+
+    pub(crate) fn authorization(&self) -> Option<Vec<u8>> {
+        let (username, password) = self.credentials.as_ref()?;
+        let mut value = BASIC.to_vec();
+        value.extend(base64::encode(format!("{username}:{password}")).as_bytes());
+
+        Some(value)
+    }
+
+    fn http_connect(&self, stream: &mut TcpStream, url: &Url) -> Success {
+        stream
+            .write_all(&self.encode_connect(url))
+            .timeout_as(TimeoutKind::Connect)?;
+        stream.flush().timeout_as(TimeoutKind::Connect)?;
+        let status = read_status(stream)?;
+        match status {
+            200..=299 => Ok(()),
+            PROXY_AUTH_REQUIRED => Err(Error::connection(
+                "proxy authentication required",
+                status.some_box(),
+            )),
+            _ => Err(Error::connection(
+                &format!("proxy refused tunnel with status {status}"),
+                status.some_box(),
+            )),
+        }
+    }
+
+    fn encode_connect(&self, url: &Url) -> Vec<u8> {
+        let authority = url.authority();
+        let mut message = CONNECT.to_vec();
+        message.extend(authority.as_bytes());
+        message.push(SP);
+        message.extend(HTTP_VERSION);
+        message.extend(CRLF);
+        message.extend(HOST);
+        message.extend(COLSP);
+        message.extend(authority.as_bytes());
+        message.extend(CRLF);
+        if let Some(authorization) = self.authorization() {
+            message.extend(PROXY_AUTHORIZATION);
+            message.extend(COLSP);
+            message.extend(authorization);
+            message.extend(CRLF);
+        }
+        message.extend(CRLF);
+
+        message
+    }
+}
+
+fn read_status(stream: &mut TcpStream) -> Result<u16> {
+    let mut head = Vec::new();
+    let mut byte = [0; 1];
+    while !head.ends_with(HEAD_END) {
+        if stream.read(&mut byte).timeout_as(TimeoutKind::Connect)? == 0 || head.len() > MAX_HEAD {
+            return Err(Error::connection("invalid proxy response", None));
+        }
+        head.push(byte[0]);
+    }
+
+    head.split(|b| *b == SP)
+        .nth(1)
+        .and_then(|code| String::from_utf8_lossy(code).parse().ok())
+        .ok_or_else(|| Error::connection("invalid proxy response", None))
+}
@@ -0,0 +1,173 @@
+use crate::http::error::{IoTimeout, SomeError, TimeoutKind};
+use crate::http::url::Url;
+use crate::http::{Error, Result, Success};
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+
+const VERSION: u8 = 0x05;
+const AUTH_VERSION: u8 = 0x01;
+const NO_AUTH: u8 = 0x00;
+const USER_PASS: u8 = 0x02;
+const NO_ACCEPTABLE_METHODS: u8 = 0xFF;
+const CMD_CONNECT: u8 = 0x01;
+const RESERVED: u8 = 0x00;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+const SUCCEEDED: u8 = 0x00;
+
+pub(crate) fn connect(
+    stream: &mut TcpStream,
+    url: &Url,
+    credentials: Option<&(String, String)>,
+    remote_dns: bool,
+) -> Success {
+    let greeting = match credentials {
+        Some(_) => vec![VERSION, 2, NO_AUTH, USER_PASS],
+        None => vec![VERSION, 1, NO_AUTH],
+    };
+    stream
+        .write_all(&greeting)
+        .timeout_as(TimeoutKind::Connect)?;
+    let mut choice = [0; 2];
+    stream
+        .read_exact(&mut choice)
+        .timeout_as(TimeoutKind::Connect)?;
+    if choice[0] != VERSION {
+        return Err(Error::connection("invalid socks5 server response", None));
+    }
+    match (choice[1], credentials) {
+        (NO_AUTH, _) => {}
+        (USER_PASS, Some((username, password))) => authenticate(stream, username, password)?,
+        (NO_ACCEPTABLE_METHODS, _) => {
+            return Err(Error::connection(
+                "socks5 server rejected authentication methods",
+                None,
+            ))
+        }
+        (method, _) => {
+            return Err(Error::connection(
+                "socks5 server selected unsupported authentication method",
+                method.some_box(),
+            ))
+        }
+    }
+    stream
+        .write_all(&encode_connect(url, remote_dns)?)
+        .timeout_as(TimeoutKind::Connect)?;
+    stream.flush().timeout_as(TimeoutKind::Connect)?;
+    read_reply(stream)
+}
+
+fn authenticate(stream: &mut TcpStream, username: &str, password: &str) -> Success {
+    if username.len() > u8::MAX as usize || password.len() > u8::MAX as usize {
+        return Err(Error::user("socks5 credentials must not exceed 255 bytes"));
+    }
+    let mut message = vec![AUTH_VERSION, username.len() as u8];
+    message.extend(username.as_bytes());
+    message.push(password.len() as u8);
+    message.extend(password.as_bytes());
+    stream
+        .write_all(&message)
+        .timeout_as(TimeoutKind::Connect)?;
+    let mut status = [0; 2];
+    stream
+        .read_exact(&mut status)
+        .timeout_as(TimeoutKind::Connect)?;
+    match status[1] {
+        SUCCEEDED => Ok(()),
+        _ => Err(Error::connection(
+            "socks5 authentication failed",
+            status[1].some_box(),
+        )),
+    }
+}
+
+fn encode_connect(url: &Url, remote_dns: bool) -> Result<Vec<u8>> {
+    let host = String::from_utf8_lossy(&url.host);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let address = match (host.parse(), remote_dns) {
+        (Ok(ip), _) => Some(ip),
+        (Err(_), true) => None,
+        (Err(_), false) => Some(resolve(host, url.port())?),
+    };
+    let mut message = vec![VERSION, CMD_CONNECT, RESERVED];
+    match address {
+        Some(IpAddr::V4(ip)) => {
+            message.push(ATYP_IPV4);
+            message.extend(ip.octets());
+        }
+        Some(IpAddr::V6(ip)) => {
+            message.push(ATYP_IPV6);
+            message.extend(ip.octets());
+        }
+        None => {
+            if host.len() > u8::MAX as usize {
+                return Err(Error::user("host name too long for socks5"));
+            }
+            message.push(ATYP_DOMAIN);
+            message.push(host.len() as u8);
+            message.extend(host.as_bytes());
+        }
+    }
+    message.extend(url.port().to_be_bytes());
+
+    Ok(message)
+}
+
+fn resolve(host: &str, port: u16) -> Result<IpAddr> {
+    (host, port)
+        .to_socket_addrs()
+        .map_err(|e| Error::connection("could not resolve host", e.some_box()))?
+        .next()
+        .map(|address| address.ip())
+        .ok_or_else(|| Error::connection("could not resolve host", None))
+}
+
+fn read_reply(stream: &mut TcpStream) -> Success {
+    let mut head = [0; 4];
+    stream
+        .read_exact(&mut head)
+        .timeout_as(TimeoutKind::Connect)?;
+    if head[0] != VERSION {
+        return Err(Error::connection("invalid socks5 server response", None));
+    }
+    if head[1] != SUCCEEDED {
+        return Err(Error::connection(
+            reply_message(head[1]),
+            head[1].some_box(),
+        ));
+    }
+    let address_len = match head[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => {
+            let mut len = [0; 1];
+            stream
+                .read_exact(&mut len)
+                .timeout_as(TimeoutKind::Connect)?;
+            len[0] as usize
+        }
+        _ => return Err(Error::connection("invalid socks5 server response", None)),
+    };
+    let mut bound = vec![0; address_len + 2];
+    stream
+        .read_exact(&mut bound)
+        .timeout_as(TimeoutKind::Connect)?;
+
+    Ok(())
+}
+
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "socks5 general server failure",
+        0x02 => "socks5 connection not allowed by ruleset",
+        0x03 => "socks5 network unreachable",
+        0x04 => "socks5 host unreachable",
+        0x05 => "socks5 connection refused",
+        0x06 => "socks5 ttl expired",
+        0x07 => "socks5 command not supported",
+        0x08 => "socks5 address type not supported",
+        _ => "socks5 connection failed",
+    }
+}
@@ -1,7 +1,17 @@
 use super::{Method, Protocol};
+use crate::http::body::BodyReader;
+use crate::http::encoding::BodyCompression;
+pub use crate::http::header as headers;
+use crate::http::header::{values, HeaderMap, EXPECT};
 use crate::http::url::Url;
-use crate::http::utf8_utils::EQUALS;
-use std::collections::HashMap;
+use std::io::Read;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ArrayFormat {
+    #[default]
+    Repeat,
+    Brackets,
+}
 
 #[derive(Clone, Debug)]
 pub struct RequestBuilder {
@@ -9,69 +19,64 @@ pub struct RequestBuilder {
     pub method: Method,
     pub url: Url,
     pub body: Option<Vec<u8>>,
-    pub headers: HashMap<Vec<u8>, Vec<u8>>,
+    pub body_reader: Option<BodyReader>,
+    pub compression: Option<BodyCompression>,
+    pub headers: HeaderMap,
+    pub(crate) unparsed_url: Option<String>,
 }
 
 impl RequestBuilder {
-    pub fn get(url: &str) -> Self {
+    pub fn new(method: Method, url: Url) -> Self {
         Self {
             protocol: Protocol::default(),
-            method: Method::GET,
-            url: url.as_bytes().into(),
+            method: method.normalize(),
+            url,
             body: None,
+            body_reader: None,
+            compression: None,
             headers: Default::default(),
+            unparsed_url: None,
         }
     }
-    pub fn post(url: &str) -> Self {
-        Self {
-            protocol: Protocol::default(),
-            method: Method::POST,
-            url: url.as_bytes().into(),
-            body: None,
-            headers: Default::default(),
+    fn parse(method: Method, url: &str) -> Self {
+        match Url::parse(url) {
+            Ok(parsed) => Self::new(method, parsed),
+            Err(_) => Self {
+                unparsed_url: Some(url.to_string()),
+                ..Self::new(method, Url::default())
+            },
         }
     }
+    pub fn get(url: &str) -> Self {
+        Self::parse(Method::GET, url)
+    }
+    pub fn head(url: &str) -> Self {
+        Self::parse(Method::HEAD, url)
+    }
+    pub fn post(url: &str) -> Self {
+        Self::parse(Method::POST, url)
+    }
     pub fn put(url: &str) -> Self {
-        Self {
-            protocol: Protocol::default(),
-            method: Method::PUT,
-            url: url.as_bytes().into(),
-            body: None,
-            headers: Default::default(),
-        }
+        Self::parse(Method::PUT, url)
     }
     pub fn patch(url: &str) -> Self {
-        Self {
-            protocol: Protocol::default(),
-            method: Method::PATCH,
-            url: url.as_bytes().into(),
-            body: None,
-            headers: Default::default(),
-        }
+        Self::parse(Method::PATCH, url)
     }
     pub fn delete(url: &str) -> Self {
-        Self {
-            protocol: Protocol::default(),
-            method: Method::DELETE,
-            url: url.as_bytes().into(),
-            body: None,
-            headers: Default::default(),
-        }
+        Self::parse(Method::DELETE, url)
     }
     pub fn connect(url: &str) -> Self {
-        Self {
-            protocol: Protocol::default(),
-            method: Method::CONNECT,
-            url: url.as_bytes().into(),
-            body: None,
-            headers: Default::default(),
-        }
+        Self::parse(Method::CONNECT, url)
+    }
+    pub fn options(url: &str) -> Self {
+        Self::parse(Method::OPTIONS, url)
+    }
+    pub fn trace(url: &str) -> Self {
+        Self::parse(Method::TRACE, url)
     }
     pub fn extend_query<T: AsRef<[u8]>>(&mut self, query: Vec<(T, T)>) {
         for (key, value) in query.into_iter() {
-            self.url.query.extend_from_slice(key.as_ref());
-            self.url.query.push(EQUALS);
-            self.url.query.extend_from_slice(value.as_ref());
+            self.url.append_query_pair(key.as_ref(), value.as_ref());
         }
     }
 
@@ -81,13 +86,44 @@ impl RequestBuilder {
         self
     }
 
+    pub fn extend_query_array<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        values: &[V],
+        format: ArrayFormat,
+    ) {
+        let key = match format {
+            ArrayFormat::Repeat => key.as_ref().to_vec(),
+            ArrayFormat::Brackets => [key.as_ref(), b"[]"].concat(),
+        };
+        for value in values {
+            self.url.append_query_pair(&key, value.as_ref());
+        }
+    }
+
+    pub fn query_array<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        mut self,
+        key: K,
+        values: &[V],
+        format: ArrayFormat,
+    ) -> Self {
+        self.extend_query_array(key, values, format);
+
+        self
+    }
+
+    pub fn merge_query(&mut self, query: &[u8]) {
+        self.url.merge_query(query);
+    }
+
     pub fn set_query(&mut self, query: Vec<u8>) {
         self.url.query = query;
     }
 
     pub fn extend_headers(&mut self, headers: Vec<(&[u8], &[u8])>) {
-        let headers = headers.iter().map(|(k, v)| (k.to_vec(), v.to_vec()));
-        self.headers.extend(headers);
+        for (key, value) in headers {
+            self.headers.insert(key, value);
+        }
     }
 
     pub fn headers(mut self, headers: Vec<(&[u8], &[u8])>) -> Self {
@@ -98,7 +134,12 @@ impl RequestBuilder {
 
     pub fn insert_header(&mut self, header: (&[u8], &[u8])) {
         let (key, value) = header;
-        self.headers.insert(key.to_vec(), value.to_vec());
+        self.headers.insert(key, value);
+    }
+
+    pub fn append_header(&mut self, header: (&[u8], &[u8])) {
+        let (key, value) = header;
+        self.headers.append(key, value);
     }
 
     pub fn header(mut self, header: (&[u8], &[u8])) -> Self {
@@ -109,6 +150,7 @@ impl RequestBuilder {
 
     pub fn body_mut(&mut self, body: &[u8]) {
         self.body = Some(body.to_vec());
+        self.body_reader = None;
     }
 
     pub fn body(self, body: &[u8]) -> Self {
@@ -117,41 +159,60 @@ impl RequestBuilder {
             method: self.method,
             url: self.url,
             body: Some(body.to_vec()),
+            body_reader: None,
+            compression: self.compression,
             headers: self.headers,
+            unparsed_url: self.unparsed_url,
         }
     }
 
+    pub fn body_reader_mut<R: Read + Send + 'static>(&mut self, reader: R, length: Option<u64>) {
+        self.body = None;
+        self.body_reader = Some(BodyReader::new(reader, length));
+    }
+
+    pub fn body_reader<R: Read + Send + 'static>(mut self, reader: R, length: Option<u64>) -> Self {
+        self.body_reader_mut(reader, length);
+
+        self
+    }
+
+    pub fn compress_mut(&mut self, compression: BodyCompression) {
+        self.compression = Some(compression);
+    }
+
+    pub fn compress(mut self, compression: BodyCompression) -> Self {
+        self.compress_mut(compression);
+
+        self
+    }
+
+    pub fn expect_continue(mut self) -> Self {
+        self.insert_header((EXPECT, values::CONTINUE));
+
+        self
+    }
+
+    pub fn expects_continue(&self) -> bool {
+        let has_body = self.body.is_some() || self.body_reader.is_some();
+
+        has_body
+            && self
+                .headers
+                .get(EXPECT)
+                .is_some_and(|value| value.trim_ascii().eq_ignore_ascii_case(values::CONTINUE))
+    }
+
     pub fn protocol(self, protocol: Protocol) -> Self {
         Self {
             protocol,
             method: self.method,
             url: self.url,
             body: self.body,
+            body_reader: self.body_reader,
+            compression: self.compression,
             headers: self.headers,
+            unparsed_url: self.unparsed_url,
         }
     }
 }
-
-pub mod headers {
-    pub const HOST: &[u8] = b"Host";
-    pub const AUTHORIZATION: &[u8] = b"Authorization";
-    pub const CONTENT_LENGTH: &[u8] = b"Content-Length";
-    pub const USER_AGENT: &[u8] = b"User-Agent";
-    pub const ACCEPT: &[u8] = b"Accept";
-    pub const ACCEPT_CHARSET: &[u8] = b"Accept-Charset";
-    pub const ACCEPT_LANGUAGE: &[u8] = b"Accept-Language";
-    pub const CONNECTION: &[u8] = b"Connection";
-    pub const MAX_FORWARDS: &[u8] = b"Max-Forwards";
-    pub const FROM: &[u8] = b"From";
-    pub const REFERER: &[u8] = b"Referer";
-    pub const CONTENT_TYPE: &[u8] = b"Content-Type";
-    pub mod values {
-        pub const ALL: &[u8] = b"*/*";
-        pub const JSON: &[u8] = b"application/json";
-        pub const UTF8: &[u8] = b"charset=utf-8";
-        pub const TEXT_HTML: &[u8] = b"text/html";
-        pub const TEXT_PLAIN: &[u8] = b"text/plain";
-        pub const EN_US: &[u8] = b"en_US";
-        pub const KEEP_ALIVE: &[u8] = b"keep-alive";
-    }
-}
@@ -1,5 +1,6 @@
 pub const CONTINUE: u16 = 100;
 pub const SWITCHING_PROTOCOLS: u16 = 101;
+pub const EARLY_HINTS: u16 = 103;
 
 pub const OK: u16 = 200;
 
@@ -16,3 +17,7 @@ pub const TOO_MANY_REQUESTS: u16 = 429;
 
 pub const INTERNAL_SERVER_ERROR: u16 = 500;
 pub const HTTP_VERSION_NOT_SUPPORTED: u16 = 505;
+
+pub fn is_informational(status_code: u16) -> bool {
+    (100..200).contains(&status_code) && status_code != SWITCHING_PROTOCOLS
+}
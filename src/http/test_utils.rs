@@ -1,5 +1,69 @@
+#[cfg(feature = "http2")]
+use crate::http::http2::codec::frames::settings::Setting;
+#[cfg(feature = "http2")]
+use crate::http::http2::codec::frames::{
+    headers, DataFrame, FrameHeader, FrameKind, HeadersFrame, SettingsFrame, END_STREAM, PREFACE,
+};
+use crate::http::options::ConnOptions;
 use crate::http::request::RequestBuilder;
-use crate::http::{HttpClient, Protocol, Response};
+use crate::http::tls::{parse_pem, CERTIFICATE, PRIVATE_KEY};
+use crate::http::transport::{Connector, Transport};
+use crate::http::url::Url;
+use crate::http::{HttpClient, Protocol, Response, Result};
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig, ServerConnection, StreamOwned};
+use std::cell::Cell;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub(crate) const CA: &[u8] = include_bytes!("tls/tests/ca.pem");
+pub(crate) const SERVER_CERT: &[u8] = include_bytes!("tls/tests/server.pem");
+pub(crate) const SERVER_KEY: &[u8] = include_bytes!("tls/tests/server.key");
+pub(crate) const CLIENT_CERT: &[u8] = include_bytes!("tls/tests/client.pem");
+pub(crate) const CLIENT_KEY: &[u8] = include_bytes!("tls/tests/client.key");
+pub(crate) const SERVER_PIN: &str = "sha256/Fs/VfO8nKSAZDtret+fzoVH64Rft65A63HWaNfg569g=";
+pub(crate) const CA_PIN: &str = "WbNi1eL+UVI1Eoy6n4rQZuHOZ3XEoKooNmxFpPp6txk=";
+
+pub(crate) fn tls_server(require_client_cert: bool) -> u16 {
+    let chain = parse_pem(SERVER_CERT, CERTIFICATE)
+        .unwrap()
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    let key = PrivateKey(parse_pem(SERVER_KEY, PRIVATE_KEY).unwrap().remove(0));
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let config = match require_client_cert {
+        true => {
+            let mut roots = RootCertStore::empty();
+            roots
+                .add(&Certificate(parse_pem(CA, CERTIFICATE).unwrap().remove(0)))
+                .unwrap();
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+        }
+        false => builder.with_no_client_auth(),
+    }
+    .with_single_cert(chain, key)
+    .unwrap();
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let conn = ServerConnection::new(config).unwrap();
+        let mut stream = StreamOwned::new(conn, stream);
+        read_request_head(&mut stream);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecure")
+            .unwrap();
+        stream.flush().unwrap();
+    });
+
+    port
+}
 
 pub(crate) fn print_results(results: Vec<std::time::Duration>) {
     for (_i, _end) in results.iter().enumerate() {
@@ -51,3 +115,206 @@ pub(crate) fn iterate_request(
     }
     print_results(results);
 }
+
+pub(crate) fn serve_once(response: &[u8]) -> (u16, JoinHandle<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let response = response.to_vec();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let request = read_request_head(&mut stream);
+        stream.write_all(&response).unwrap();
+        stream.flush().unwrap();
+
+        request
+    });
+
+    (port, handle)
+}
+
+pub(crate) fn read_request_head<S: Read>(stream: &mut S) -> Vec<u8> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).unwrap();
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    request
+}
+
+pub(crate) struct Duplex {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+    pending: Vec<u8>,
+    read_timeout: Cell<Option<Duration>>,
+}
+
+pub(crate) fn duplex() -> (Duplex, Duplex) {
+    let (client_tx, server_rx) = channel();
+    let (server_tx, client_rx) = channel();
+    let client = Duplex {
+        tx: client_tx,
+        rx: client_rx,
+        pending: Vec::new(),
+        read_timeout: Cell::new(None),
+    };
+    let server = Duplex {
+        tx: server_tx,
+        rx: server_rx,
+        pending: Vec::new(),
+        read_timeout: Cell::new(None),
+    };
+
+    (client, server)
+}
+
+impl Read for Duplex {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.is_empty() {
+            let received = match self.read_timeout.get() {
+                Some(timeout) => self.rx.recv_timeout(timeout),
+                None => self.rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(bytes) => self.pending = bytes,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(std::io::Error::from(std::io::ErrorKind::TimedOut))
+                }
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+        let read = buf.len().min(self.pending.len());
+        buf[..read].copy_from_slice(&self.pending[..read]);
+        self.pending.drain(..read);
+
+        Ok(read)
+    }
+}
+
+impl Write for Duplex {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx
+            .send(buf.to_vec())
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Duplex {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.read_timeout.set(timeout);
+
+        Ok(())
+    }
+}
+
+pub(crate) struct MemoryConnector {
+    pub(crate) transports: Mutex<Vec<Duplex>>,
+}
+
+impl Connector for MemoryConnector {
+    fn connect(&self, _url: &Url, _options: &ConnOptions) -> Result<Box<dyn Transport>> {
+        Ok(Box::new(self.transports.lock().unwrap().remove(0)))
+    }
+}
+
+#[cfg(feature = "http2")]
+pub(crate) fn read_preface<S: Read>(stream: &mut S) -> Vec<(u16, u32)> {
+    let mut preface = [0; 24];
+    stream.read_exact(&mut preface).unwrap();
+    assert_eq!(preface, PREFACE);
+    let (header, settings) = read_frame(stream);
+    assert_eq!(header.kind, FrameKind::Setting);
+
+    settings
+        .chunks(6)
+        .map(|setting| {
+            (
+                u16::from_be_bytes([setting[0], setting[1]]),
+                u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]),
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "http2")]
+pub(crate) fn http2_handshake<S: Read + Write>(
+    stream: &mut S,
+    settings: Vec<Setting>,
+) -> Vec<(u16, u32)> {
+    let advertised = read_preface(stream);
+    let mut frames =
+        SettingsFrame::new(FrameHeader::new(FrameKind::Setting, 0, 0), settings).encode();
+    frames.extend(SettingsFrame::ack());
+    stream.write_all(&frames).unwrap();
+
+    advertised
+}
+
+#[cfg(feature = "http2")]
+pub(crate) fn read_frame<S: Read>(stream: &mut S) -> (FrameHeader, Vec<u8>) {
+    let mut header = [0; 9];
+    stream.read_exact(&mut header).unwrap();
+    let header = FrameHeader::try_from(header.as_slice()).unwrap();
+    let mut payload = vec![0; header.length as usize];
+    stream.read_exact(&mut payload).unwrap();
+
+    (header, payload)
+}
+
+#[cfg(feature = "http2")]
+pub(crate) fn read_header_frames<S: Read>(stream: &mut S, count: usize) -> Vec<u32> {
+    let mut streams = Vec::new();
+    while streams.len() < count {
+        let (header, _) = read_frame(stream);
+        if header.kind == FrameKind::Headers {
+            streams.push(header.stream_identifier);
+        }
+    }
+
+    streams
+}
+
+#[cfg(feature = "http2")]
+pub(crate) fn http2_headers(stream: u32, flags: u8, blocks: Vec<u8>) -> Vec<u8> {
+    HeadersFrame::new(
+        FrameHeader::new(
+            FrameKind::Headers,
+            headers::Flags::EndHeaders as u8 | flags,
+            stream,
+        ),
+        headers::Headers {
+            pad_length: None,
+            is_exclusive: None,
+            stream_dependency: None,
+            weight: None,
+            blocks,
+            padding: None,
+        },
+    )
+    .encode()
+}
+
+#[cfg(feature = "http2")]
+pub(crate) fn http2_data(stream: u32, flags: u8, data: &[u8]) -> Vec<u8> {
+    DataFrame::parse_from_payload(FrameHeader::new(FrameKind::Data, flags, stream), data)
+        .unwrap()
+        .encode()
+}
+
+#[cfg(feature = "http2")]
+pub(crate) fn http2_response(stream: u32, body: &[u8]) -> Vec<u8> {
+    let mut frames = http2_headers(stream, 0, vec![0x88]);
+    frames.extend(http2_data(stream, END_STREAM, body));
+
+    frames
+}
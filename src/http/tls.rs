@@ -0,0 +1,310 @@
+use crate::http::error::{SomeError, TimeoutKind};
+use crate::http::{Error, Result};
+use ring::digest::{digest, SHA256};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+#[cfg(test)]
+mod tests;
+
+const PEM_BEGIN: &str = "-----BEGIN ";
+const PEM_END: &str = "-----END ";
+const PEM_DASHES: &str = "-----";
+pub(crate) const CERTIFICATE: &str = "CERTIFICATE";
+pub(crate) const PRIVATE_KEY: &str = "PRIVATE KEY";
+const PIN_PREFIX: &str = "sha256/";
+const PIN_MISMATCH: &str = "no certificate matched the pinned public keys";
+const DER_VERSION: u8 = 0xA0;
+
+pub type SpkiPin = [u8; 32];
+
+#[derive(Debug, Clone)]
+pub struct PinMismatch {
+    pub host: String,
+}
+
+impl Display for PinMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} for {}", PIN_MISMATCH, self.host)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TlsOptions {
+    pub(crate) roots: Vec<Certificate>,
+    pub(crate) webpki_roots: bool,
+    pub(crate) identity: Option<(Vec<Certificate>, PrivateKey)>,
+    pub(crate) verify_certificates: bool,
+    pub(crate) pins: HashMap<String, Vec<SpkiPin>>,
+}
+
+impl Default for TlsOptions {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            webpki_roots: true,
+            identity: None,
+            verify_certificates: true,
+            pins: HashMap::new(),
+        }
+    }
+}
+
+impl TlsOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_root_der(mut self, der: &[u8]) -> Self {
+        self.roots.push(Certificate(der.to_vec()));
+
+        self
+    }
+
+    pub fn add_root_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let certificates = parse_pem(pem, CERTIFICATE)?;
+        if certificates.is_empty() {
+            return Err(Error::user("no certificates found in pem"));
+        }
+        self.roots.extend(certificates.into_iter().map(Certificate));
+
+        Ok(self)
+    }
+
+    pub fn only_custom_roots(mut self) -> Self {
+        self.webpki_roots = false;
+
+        self
+    }
+
+    pub fn client_identity_der(mut self, cert_chain: Vec<Vec<u8>>, key: &[u8]) -> Self {
+        self.identity = Some((
+            cert_chain.into_iter().map(Certificate).collect(),
+            PrivateKey(key.to_vec()),
+        ));
+
+        self
+    }
+
+    pub fn client_identity_pem(self, cert_chain: &[u8], key: &[u8]) -> Result<Self> {
+        let chain = parse_pem(cert_chain, CERTIFICATE)?;
+        if chain.is_empty() {
+            return Err(Error::user("no certificates found in pem"));
+        }
+        let key = parse_pem(key, PRIVATE_KEY)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::user("no private key found in pem"))?;
+
+        Ok(self.client_identity_der(chain, &key))
+    }
+
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        self.verify_certificates = false;
+
+        self
+    }
+
+    pub fn pin_sha256(mut self, host: &str, pin: SpkiPin) -> Self {
+        self.pins
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .push(pin);
+
+        self
+    }
+
+    pub fn pin_sha256_base64(self, host: &str, pin: &str) -> Result<Self> {
+        let pin = pin.strip_prefix(PIN_PREFIX).unwrap_or(pin);
+        let pin = base64::decode(pin)
+            .ok()
+            .and_then(|p| SpkiPin::try_from(p.as_slice()).ok())
+            .ok_or_else(|| Error::user("pin must be a base64 encoded sha256 hash"))?;
+
+        Ok(self.pin_sha256(host, pin))
+    }
+
+    pub(crate) fn root_store(&self) -> Result<RootCertStore> {
+        let mut root_store = RootCertStore::empty();
+        if self.webpki_roots {
+            root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(
+                |ta| {
+                    rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                        ta.subject,
+                        ta.spki,
+                        ta.name_constraints,
+                    )
+                },
+            ));
+        }
+        for root in self.roots.iter() {
+            root_store
+                .add(root)
+                .map_err(|e| Error::user(&format!("invalid root certificate: {e}")))?;
+        }
+
+        Ok(root_store)
+    }
+
+    pub(crate) fn client_config(&self, host: &str, protocols: &[&[u8]]) -> Result<ClientConfig> {
+        let mut verifier: Arc<dyn ServerCertVerifier> = match self.verify_certificates {
+            true => Arc::new(WebPkiVerifier::new(self.root_store()?, None)),
+            false => Arc::new(NoVerification),
+        };
+        if let Some(pins) = self.pins.get(&host.to_ascii_lowercase()) {
+            verifier = Arc::new(PinningVerifier {
+                inner: verifier,
+                pins: pins.clone(),
+            });
+        }
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(verifier);
+        let mut config = match &self.identity {
+            Some((chain, key)) => builder
+                .with_single_cert(chain.clone(), key.clone())
+                .map_err(|e| {
+                    Error::connection("invalid client certificate", e.to_string().some_box())
+                })?,
+            None => builder.with_no_client_auth(),
+        };
+        protocols
+            .iter()
+            .for_each(|p| config.alpn_protocols.push(p.to_vec()));
+
+        Ok(config)
+    }
+}
+
+struct NoVerification;
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+struct PinningVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    pins: Vec<SpkiPin>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        let pinned = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(|cert| spki_sha256(&cert.0))
+            .any(|hash| self.pins.contains(&hash));
+        match pinned {
+            true => Ok(verified),
+            false => Err(rustls::Error::InvalidCertificateData(
+                PIN_MISMATCH.to_string(),
+            )),
+        }
+    }
+}
+
+pub(crate) fn handshake_error(e: std::io::Error, host: &str) -> Error {
+    match e.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()) {
+        Some(rustls::Error::InvalidCertificateData(message)) if message == PIN_MISMATCH => {
+            Error::connection(
+                PIN_MISMATCH,
+                PinMismatch {
+                    host: host.to_string(),
+                }
+                .some_box(),
+            )
+        }
+        _ => Error::io(e, TimeoutKind::Connect),
+    }
+}
+
+pub fn spki_sha256(cert: &[u8]) -> Option<SpkiPin> {
+    let (_, certificate, _) = der_element(cert)?;
+    let (_, tbs, _) = der_element(certificate)?;
+    let (tag, _, mut fields) = der_element(tbs)?;
+    if tag == DER_VERSION {
+        fields = der_element(fields)?.2;
+    }
+    // signature algorithm, issuer, validity and subject precede the public key
+    for _ in 0..4 {
+        fields = der_element(fields)?.2;
+    }
+    let spki = &fields[..fields.len() - der_element(fields)?.2.len()];
+
+    SpkiPin::try_from(digest(&SHA256, spki).as_ref()).ok()
+}
+
+fn der_element(bytes: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *bytes.first()?;
+    let first = *bytes.get(1)? as usize;
+    let (length, offset) = match first & 0x80 {
+        0 => (first, 2),
+        _ => {
+            let size = first & 0x7F;
+            let length = bytes
+                .get(2..2 + size)?
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+            (length, 2 + size)
+        }
+    };
+    let content = bytes.get(offset..offset + length)?;
+
+    Some((tag, content, &bytes[offset + length..]))
+}
+
+pub(crate) fn parse_pem(pem: &[u8], label: &str) -> Result<Vec<Vec<u8>>> {
+    let pem = String::from_utf8_lossy(pem);
+    let mut blocks = Vec::new();
+    let mut lines = pem.lines().map(|l| l.trim());
+    while let Some(line) = lines.next() {
+        let kind = match line
+            .strip_prefix(PEM_BEGIN)
+            .and_then(|l| l.strip_suffix(PEM_DASHES))
+        {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let mut encoded = String::new();
+        for line in lines.by_ref() {
+            if line.starts_with(PEM_END) {
+                break;
+            }
+            encoded.push_str(line);
+        }
+        if kind.ends_with(label) {
+            blocks.push(base64::decode(&encoded).map_err(|_| Error::user("malformed pem block"))?);
+        }
+    }
+
+    Ok(blocks)
+}
@@ -0,0 +1,138 @@
+use crate::http::error::ErrorKind;
+use crate::http::request::RequestBuilder;
+use crate::http::test_utils::{
+    tls_server, CA, CA_PIN, CLIENT_CERT, CLIENT_KEY, SERVER_CERT, SERVER_PIN,
+};
+use crate::http::tls::{parse_pem, spki_sha256, PinMismatch, TlsOptions, CERTIFICATE};
+use crate::http::{HttpClient, Protocol};
+
+fn get(port: u16, tls: TlsOptions) -> crate::http::Result<crate::http::Response> {
+    let mut client = HttpClient::new().tls(tls);
+    client.execute(RequestBuilder::get(&format!("localhost:{port}/")).protocol(Protocol::HTTP1))
+}
+
+#[test]
+fn untrusted_root_rejected() {
+    let port = tls_server(false);
+    let error = get(port, TlsOptions::new()).unwrap_err();
+    assert!(matches!(error.kind, ErrorKind::Connection(_)));
+}
+
+#[test]
+fn custom_root() {
+    let port = tls_server(false);
+    let tls = TlsOptions::new().add_root_pem(CA).unwrap();
+    let response = get(port, tls).unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body, b"secure");
+}
+
+#[test]
+fn replaced_roots() {
+    let port = tls_server(false);
+    let ca = parse_pem(CA, CERTIFICATE).unwrap().remove(0);
+    let tls = TlsOptions::new().only_custom_roots().add_root_der(&ca);
+    assert_eq!(get(port, tls).unwrap().body, b"secure");
+}
+
+#[test]
+fn accept_invalid_certs() {
+    let port = tls_server(false);
+    let tls = TlsOptions::new().danger_accept_invalid_certs();
+    assert_eq!(get(port, tls).unwrap().status_code, 200);
+}
+
+#[test]
+fn client_certificate_required() {
+    let port = tls_server(true);
+    let tls = TlsOptions::new().add_root_pem(CA).unwrap();
+    assert!(get(port, tls).is_err());
+}
+
+#[test]
+fn client_certificate() {
+    let port = tls_server(true);
+    let tls = TlsOptions::new()
+        .add_root_pem(CA)
+        .unwrap()
+        .client_identity_pem(CLIENT_CERT, CLIENT_KEY)
+        .unwrap();
+    assert_eq!(get(port, tls).unwrap().body, b"secure");
+}
+
+#[test]
+fn malformed_pem() {
+    assert!(TlsOptions::new()
+        .add_root_pem(b"not a certificate")
+        .is_err());
+    assert!(TlsOptions::new()
+        .client_identity_pem(CLIENT_CERT, b"")
+        .is_err());
+}
+
+#[test]
+fn spki_hash() {
+    let cert = parse_pem(SERVER_CERT, CERTIFICATE).unwrap().remove(0);
+    let expected = base64::decode(SERVER_PIN.trim_start_matches("sha256/")).unwrap();
+    assert_eq!(spki_sha256(&cert).unwrap().as_slice(), expected);
+    assert!(spki_sha256(&cert[..cert.len() / 2]).is_none());
+}
+
+#[test]
+fn pinned_leaf() {
+    let port = tls_server(false);
+    let tls = TlsOptions::new()
+        .add_root_pem(CA)
+        .unwrap()
+        .pin_sha256_base64("localhost", SERVER_PIN)
+        .unwrap();
+    assert_eq!(get(port, tls).unwrap().body, b"secure");
+}
+
+#[test]
+fn pinned_without_verification() {
+    let port = tls_server(false);
+    let tls = TlsOptions::new()
+        .danger_accept_invalid_certs()
+        .pin_sha256_base64("LOCALHOST", SERVER_PIN)
+        .unwrap();
+    assert_eq!(get(port, tls).unwrap().status_code, 200);
+    let port = tls_server(false);
+    let tls = TlsOptions::new()
+        .danger_accept_invalid_certs()
+        .pin_sha256_base64("localhost", CA_PIN)
+        .unwrap();
+    assert!(get(port, tls).is_err());
+}
+
+#[test]
+fn pin_mismatch() {
+    let port = tls_server(false);
+    let tls = TlsOptions::new()
+        .add_root_pem(CA)
+        .unwrap()
+        .pin_sha256("localhost", [0; 32]);
+    let error = get(port, tls).unwrap_err();
+    let mismatch = match error.kind {
+        ErrorKind::Connection(Some(source)) => source.downcast::<PinMismatch>().unwrap(),
+        kind => panic!("unexpected error kind {kind:?}"),
+    };
+    assert_eq!(mismatch.host, "localhost");
+}
+
+#[test]
+fn pin_other_host() {
+    let port = tls_server(false);
+    let tls = TlsOptions::new()
+        .add_root_pem(CA)
+        .unwrap()
+        .pin_sha256("example.com", [0; 32]);
+    assert_eq!(get(port, tls).unwrap().status_code, 200);
+}
+
+#[test]
+fn malformed_pin() {
+    assert!(TlsOptions::new()
+        .pin_sha256_base64("localhost", "c2hvcnQ=")
+        .is_err());
+}
@@ -0,0 +1,125 @@
+use crate::http::options::ConnOptions;
+use crate::http::url::Url;
+use crate::http::Result;
+use rustls::ClientConnection as TlsClient;
+use rustls::StreamOwned as TlsStream;
+use std::fmt::{Debug, Formatter};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+pub type ShutdownHandle = Box<dyn Fn() -> std::io::Result<()> + Send>;
+
+pub trait Transport: Read + Write + Send {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn is_secure(&self) -> bool {
+        false
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        None
+    }
+
+    fn is_closed(&self) -> bool {
+        false
+    }
+
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub trait Connector: Send + Sync {
+    fn connect(&self, url: &Url, options: &ConnOptions) -> Result<Box<dyn Transport>>;
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedConnector(pub(crate) Arc<dyn Connector>);
+
+impl Debug for SharedConnector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Connector")
+    }
+}
+
+impl Transport for TcpStream {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        let stream = self.try_clone().ok()?;
+
+        Some(Box::new(move || stream.shutdown(Shutdown::Both)))
+    }
+
+    fn is_closed(&self) -> bool {
+        if self.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let closed = match self.peek(&mut [0; 1]) {
+            Ok(0) => true,
+            Ok(_) => false,
+            Err(e) => e.kind() != ErrorKind::WouldBlock,
+        };
+        let restored = self.set_nonblocking(false).is_ok();
+
+        closed || !restored
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixStream {
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        let stream = self.try_clone().ok()?;
+
+        Some(Box::new(move || stream.shutdown(Shutdown::Both)))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl Transport for TlsStream<TlsClient, Box<dyn Transport>> {
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.conn.alpn_protocol()
+    }
+
+    fn is_secure(&self) -> bool {
+        true
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.sock.peer_addr()
+    }
+
+    fn shutdown_handle(&self) -> Option<ShutdownHandle> {
+        self.sock.shutdown_handle()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.sock.is_closed()
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.sock.set_read_timeout(timeout)
+    }
+}
@@ -0,0 +1,837 @@
+#[cfg(feature = "http2")]
+use crate::http::http2::codec::frames::*;
+use crate::http::options::{ConnOptions, Timeouts};
+use crate::http::proto_conn::ProtoConn;
+use crate::http::request::RequestBuilder;
+use crate::http::test_utils::{duplex, read_request_head, tls_server, Duplex, MemoryConnector, CA};
+#[cfg(feature = "http2")]
+use crate::http::test_utils::{
+    http2_data, http2_handshake, http2_headers, http2_response, read_frame,
+};
+use crate::http::transport::{Connector, Transport};
+use crate::http::url::Url;
+use crate::http::{HttpClient, Protocol, Result, TlsOptions};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::channel;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn http1_over_duplex() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let request = read_request_head(&mut server);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nmemory")
+            .unwrap();
+
+        request
+    });
+    let url = Url::parse("http://memory.test/status").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP1,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    assert!(!conn.transport.is_secure());
+    assert!(conn.transport.peer_addr().is_none());
+    let response = conn
+        .send_request(RequestBuilder::get("http://memory.test/status").protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(response.body, b"memory");
+    let request = handle.join().unwrap();
+    assert!(request.starts_with(b"GET /status HTTP/1.1\r\nHost: memory.test\r\n"));
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn http2_over_duplex() {
+    let (client, mut server) = duplex();
+    thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        server
+            .write_all(&http2_response(1, b"multiplexed"))
+            .unwrap();
+        let mut sink = [0; 1024];
+        while server.read(&mut sink).unwrap_or(0) != 0 {}
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP2,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let response = conn
+        .send_request(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2))
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body, b"multiplexed");
+}
+
+#[test]
+fn custom_connector() {
+    let (client, mut server) = duplex();
+    thread::spawn(move || {
+        read_request_head(&mut server);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nconnected")
+            .unwrap();
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new().connector(connector);
+    let response = client
+        .execute(RequestBuilder::get("http://sidecar/").protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(response.body, b"connected");
+}
+
+struct LoopbackConnector;
+
+impl Connector for LoopbackConnector {
+    fn connect(&self, url: &Url, _options: &ConnOptions) -> Result<Box<dyn Transport>> {
+        Ok(Box::new(TcpStream::connect(("127.0.0.1", url.port()))?))
+    }
+}
+
+#[test]
+fn tls_over_custom_connector() {
+    let port = tls_server(false);
+    let mut client = HttpClient::new()
+        .connector(LoopbackConnector)
+        .tls(TlsOptions::new().add_root_pem(CA).unwrap());
+    let response = client
+        .execute(
+            RequestBuilder::get(&format!("https://localhost:{port}/")).protocol(Protocol::HTTP1),
+        )
+        .unwrap();
+    assert_eq!(response.body, b"secure");
+}
+
+#[test]
+fn http1_streaming_body() {
+    let (client, mut server) = duplex();
+    let (release_tx, release_rx) = channel();
+    thread::spawn(move || {
+        read_request_head(&mut server);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+            .unwrap();
+        release_rx.recv().unwrap();
+        server
+            .write_all(b"5\r\nfirst\r\n7\r\n second\r\n0\r\n\r\n")
+            .unwrap();
+        read_request_head(&mut server);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nreused")
+            .unwrap();
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new().connector(connector);
+    let mut response = client
+        .send_streaming(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(
+        response.headers.get_str("transfer-encoding"),
+        Some("chunked")
+    );
+    release_tx.send(()).unwrap();
+    let mut body = String::new();
+    response.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "first second");
+    let response = client
+        .send_streaming(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(response.into_response().unwrap().body, b"reused");
+}
+
+#[test]
+fn request_while_streamed_body_is_unread() {
+    let (streaming, mut first) = duplex();
+    let (fresh, mut second) = duplex();
+    thread::spawn(move || {
+        read_request_head(&mut first);
+        first
+            .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+            .unwrap();
+        for _ in 0..16 {
+            first.write_all(b"1\r\nx\r\n").unwrap();
+        }
+        first.write_all(b"0\r\n\r\n").unwrap();
+
+        first
+    });
+    thread::spawn(move || {
+        read_request_head(&mut second);
+        second
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond")
+            .unwrap();
+
+        second
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![streaming, fresh]),
+    };
+    let mut client = HttpClient::new()
+        .connector(connector)
+        .timeouts(Timeouts::new().no_read_timeout());
+    let request = || RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP1);
+    let mut response = client.send_streaming(request()).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(client.execute(request()).unwrap().body, b"second");
+    let mut body = Vec::new();
+    response.read_to_end(&mut body).unwrap();
+    assert_eq!(body, [b'x'; 16]);
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn http2_streaming_releases_window() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        let mut frames = http2_headers(1, 0, vec![0x88]);
+        frames.extend(http2_data(1, 0, &[b'a'; 16_384]));
+        frames.extend(http2_data(1, END_STREAM, b"tail"));
+        server.write_all(&frames).unwrap();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind == FrameKind::WindowUpdate && header.stream_identifier == 1 {
+                return u32::from_be_bytes(payload.try_into().unwrap());
+            }
+        }
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new().connector(connector);
+    let mut response = client
+        .send_streaming(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2))
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    let mut body = Vec::new();
+    response.read_to_end(&mut body).unwrap();
+    assert_eq!(body.len(), 16_388);
+    assert!(body.ends_with(b"tail"));
+    assert_eq!(handle.join().unwrap(), 16_384);
+}
+
+fn read_until(server: &mut Duplex, end: &[u8]) -> Vec<u8> {
+    let mut received = Vec::new();
+    let mut buffer = [0; 1024];
+    while !received.ends_with(end) {
+        let read = server.read(&mut buffer).unwrap();
+        assert_ne!(read, 0, "connection closed before {end:?}");
+        received.extend_from_slice(&buffer[..read]);
+    }
+
+    received
+}
+
+#[test]
+fn http1_chunked_upload() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let request = read_until(&mut server, b"0\r\n\r\n");
+        server
+            .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+
+        request
+    });
+    let url = Url::parse("http://memory.test/upload").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP1,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let mut source = vec![b'x'; 20_000];
+    source.extend(b"end");
+    let response = conn
+        .send_request(
+            RequestBuilder::post("http://memory.test/upload")
+                .protocol(Protocol::HTTP1)
+                .body_reader(std::io::Cursor::new(source), None),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 201);
+    let request = handle.join().unwrap();
+    let head_end = request.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+    assert!(request[..head_end]
+        .windows(26)
+        .any(|w| w == b"Transfer-Encoding: chunked"));
+    let mut expected = b"4000\r\n".to_vec();
+    expected.extend(vec![b'x'; 16_384]);
+    expected.extend(b"\r\nE23\r\n");
+    expected.extend(vec![b'x'; 3_616]);
+    expected.extend(b"end\r\n0\r\n\r\n");
+    assert_eq!(&request[head_end..], expected.as_slice());
+}
+
+#[test]
+fn http1_sized_upload() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let request = read_until(&mut server, b"payload");
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+
+        request
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP1,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let response = conn
+        .send_request(
+            RequestBuilder::put("http://memory.test/")
+                .protocol(Protocol::HTTP1)
+                .header((b"Content-Length", b"7"))
+                .body_reader(&b"payload and more"[..], Some(7)),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    let request = handle.join().unwrap();
+    assert!(request.ends_with(b"Content-Length: 7\r\n\r\npayload"));
+    assert_eq!(
+        request
+            .windows(15)
+            .filter(|w| w.eq_ignore_ascii_case(b"content-length:"))
+            .count(),
+        1
+    );
+    let short = conn.send_request(
+        RequestBuilder::put("http://memory.test/")
+            .protocol(Protocol::HTTP1)
+            .body_reader(&b"short"[..], Some(10)),
+    );
+    assert!(short.is_err());
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn http2_upload_data_frames() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        let mut uploaded = Vec::new();
+        let mut data_frames = 0;
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            if header.kind == FrameKind::Data {
+                assert_eq!(header.stream_identifier, 1);
+                assert!(header.length <= 16_384);
+                data_frames += 1;
+                uploaded.extend(payload);
+                if header.flags & END_STREAM != 0 {
+                    break;
+                }
+            }
+        }
+        server
+            .write_all(&http2_headers(1, END_STREAM, vec![0x88]))
+            .unwrap();
+
+        (uploaded, data_frames)
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP2,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let source: Vec<u8> = (0..40_000).map(|i| (i % 251) as u8).collect();
+    let response = conn
+        .send_request(
+            RequestBuilder::post("http://memory.test/")
+                .protocol(Protocol::HTTP2)
+                .body_reader(std::io::Cursor::new(source.clone()), None),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    let (uploaded, data_frames) = handle.join().unwrap();
+    assert_eq!(uploaded, source);
+    assert_eq!(data_frames, 4);
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn http2_short_upload_is_cancelled() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        let mut headers = Vec::new();
+        loop {
+            let (header, payload) = read_frame(&mut server);
+            match header.kind {
+                FrameKind::Headers => headers = hpack::Decoder::new().decode(&payload).unwrap(),
+                FrameKind::Data => assert_eq!(header.flags & END_STREAM, 0),
+                FrameKind::RstStream => return (headers, payload),
+                _ => {}
+            }
+        }
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP2,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let error = conn
+        .send_request(
+            RequestBuilder::post("http://memory.test/")
+                .protocol(Protocol::HTTP2)
+                .body_reader(&b"short"[..], Some(10)),
+        )
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        "request body ended before its declared length"
+    );
+    let (headers, code) = handle.join().unwrap();
+    assert!(headers.contains(&(b"content-length".to_vec(), b"10".to_vec())));
+    assert_eq!(code, (ErrorCode::Cancel as u32).to_be_bytes());
+}
+
+#[test]
+fn http1_expect_continue_waits_for_interim() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let head = read_until(&mut server, b"\r\n\r\n");
+        server
+            .write_all(
+                b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n\
+                  HTTP/1.1 100 Continue\r\n\r\n",
+            )
+            .unwrap();
+        read_until(&mut server, b"payload");
+        server
+            .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok")
+            .unwrap();
+
+        head
+    });
+    let hints = std::sync::Arc::new(Mutex::new(Vec::new()));
+    let recorded = hints.clone();
+    let options = ConnOptions::default().on_informational(move |head| {
+        recorded.lock().unwrap().push((
+            head.status_code,
+            head.headers.get_str("link").map(str::to_string),
+        ))
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP1, &options).unwrap();
+    let response = conn
+        .send_request(
+            RequestBuilder::put("http://memory.test/")
+                .protocol(Protocol::HTTP1)
+                .body(b"payload")
+                .expect_continue(),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 201);
+    assert_eq!(response.body, b"ok");
+    assert!(!conn.closing);
+    let head = handle.join().unwrap();
+    assert!(head.ends_with(b"Expect: 100-continue\r\nContent-Length: 7\r\n\r\n"));
+    assert_eq!(
+        *hints.lock().unwrap(),
+        vec![(103, Some("</style.css>; rel=preload".to_string()))]
+    );
+}
+
+#[test]
+fn http1_expect_continue_early_final_status() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        read_until(&mut server, b"\r\n\r\n");
+        server
+            .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 6\r\n\r\ndenied")
+            .unwrap();
+        let mut rest = Vec::new();
+        server.read_to_end(&mut rest).unwrap();
+
+        rest
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP1,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let response = conn
+        .send_request(
+            RequestBuilder::post("http://memory.test/")
+                .protocol(Protocol::HTTP1)
+                .body_reader(&b"payload"[..], Some(7))
+                .expect_continue(),
+        )
+        .unwrap();
+    assert_eq!(response.status_code, 401);
+    assert_eq!(response.body, b"denied");
+    assert!(conn.closing);
+    drop(conn);
+    assert!(handle.join().unwrap().is_empty());
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn http2_informational_headers_are_skipped() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        http2_handshake(&mut server, Vec::new());
+        let hints = hpack::Encoder::new().encode(vec![
+            (&b":status"[..], &b"103"[..]),
+            (&b"link"[..], &b"</app.js>; rel=preload"[..]),
+        ]);
+        let mut frames = http2_headers(1, 0, hints);
+        frames.extend(http2_headers(1, END_STREAM, vec![0x88]));
+        server.write_all(&frames).unwrap();
+
+        server
+    });
+    let hints = std::sync::Arc::new(Mutex::new(Vec::new()));
+    let recorded = hints.clone();
+    let options = ConnOptions::default().on_informational(move |head| {
+        recorded
+            .lock()
+            .unwrap()
+            .push(head.headers.get_str("link").map(str::to_string));
+        assert!(!head.headers.contains(":status"));
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP2, &options).unwrap();
+    let response = conn
+        .send_request(RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP2))
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    assert!(response
+        .headers
+        .iter()
+        .all(|(name, _)| !name.starts_with(b":")));
+    assert_eq!(
+        *hints.lock().unwrap(),
+        vec![Some("</app.js>; rel=preload".to_string())]
+    );
+    drop(handle.join().unwrap());
+}
+
+#[test]
+fn idempotent_request_replayed_on_fresh_connection() {
+    let (stale, mut first) = duplex();
+    let (fresh, mut second) = duplex();
+    thread::spawn(move || {
+        read_request_head(&mut first);
+        first
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nfirst")
+            .unwrap();
+    });
+    let handle = thread::spawn(move || {
+        let head = read_request_head(&mut second);
+        second
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond")
+            .unwrap();
+
+        head
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![stale, fresh]),
+    };
+    let mut client = HttpClient::new().connector(connector);
+    let first = client
+        .execute(RequestBuilder::get("http://memory.test/a").protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(first.body, b"first");
+    let second = client
+        .execute(RequestBuilder::get("http://memory.test/b").protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(second.body, b"second");
+    assert!(handle.join().unwrap().starts_with(b"GET /b HTTP/1.1\r\n"));
+}
+
+#[test]
+fn unsafe_request_is_not_replayed() {
+    let (stale, mut server) = duplex();
+    thread::spawn(move || {
+        read_request_head(&mut server);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let connector = MemoryConnector {
+        transports: Mutex::new(Vec::new()),
+    };
+    let options = ConnOptions::default().connector(connector);
+    let mut conn =
+        ProtoConn::with_transport(Box::new(stale), &url, Protocol::HTTP1, &options).unwrap();
+    conn.exchange(RequestBuilder::get("http://memory.test/"), false)
+        .unwrap();
+    let posted = conn.exchange(
+        RequestBuilder::post("http://memory.test/")
+            .protocol(Protocol::HTTP1)
+            .body(b"once"),
+        false,
+    );
+    assert!(posted.is_err());
+}
+
+#[test]
+fn malformed_response_is_not_replayed() {
+    let (client, mut first) = duplex();
+    let (fresh, mut second) = duplex();
+    thread::spawn(move || {
+        read_request_head(&mut first);
+        first
+            .write_all(b"HTTP/1.1 200 OK\r\nbroken\r\n\r\n")
+            .unwrap();
+    });
+    thread::spawn(move || {
+        read_request_head(&mut second);
+        second
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![fresh]),
+    };
+    let options = ConnOptions::default().connector(connector);
+    let mut conn =
+        ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP1, &options).unwrap();
+    let error = conn
+        .exchange(RequestBuilder::get("http://memory.test/"), false)
+        .unwrap_err();
+    assert_eq!(error.message, "could not parse header");
+}
+
+#[test]
+fn post_after_close_delimited_response_uses_fresh_connection() {
+    let responses: [&[u8]; 2] = [
+        b"HTTP/1.1 200 OK\r\n\r\nfirst",
+        b"HTTP/1.0 200 OK\r\nContent-Length: 5\r\n\r\nfirst",
+    ];
+    for response in responses {
+        let (client, mut first) = duplex();
+        let (fresh, mut second) = duplex();
+        thread::spawn(move || {
+            read_request_head(&mut first);
+            first.write_all(response).unwrap();
+        });
+        let handle = thread::spawn(move || {
+            let request = read_request_head(&mut second);
+            second
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond")
+                .unwrap();
+
+            request
+        });
+        let url = Url::parse("http://memory.test/").unwrap();
+        let connector = MemoryConnector {
+            transports: Mutex::new(vec![fresh]),
+        };
+        let options = ConnOptions::default().connector(connector);
+        let mut conn =
+            ProtoConn::with_transport(Box::new(client), &url, Protocol::HTTP1, &options).unwrap();
+        let get = RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP1);
+        assert_eq!(conn.exchange(get, false).unwrap().body, b"first");
+        let post = RequestBuilder::post("http://memory.test/")
+            .protocol(Protocol::HTTP1)
+            .body(b"payload");
+        assert_eq!(conn.exchange(post, false).unwrap().body, b"second");
+        assert!(handle.join().unwrap().starts_with(b"POST / HTTP/1.1"));
+    }
+}
+
+#[test]
+fn connection_close_and_keep_alive_are_honoured() {
+    let (client, mut server) = duplex();
+    thread::spawn(move || {
+        read_request_head(&mut server);
+        server
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nKeep-Alive: timeout=5, max=100\r\nContent-Length: 0\r\n\r\n",
+            )
+            .unwrap();
+        read_request_head(&mut server);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    });
+    let url = Url::parse("http://memory.test/").unwrap();
+    let mut conn = ProtoConn::with_transport(
+        Box::new(client),
+        &url,
+        Protocol::HTTP1,
+        &ConnOptions::default(),
+    )
+    .unwrap();
+    let request = RequestBuilder::get("http://memory.test/").protocol(Protocol::HTTP1);
+    conn.exchange(request.clone(), false).unwrap();
+    assert_eq!(conn.keep_alive, Some(std::time::Duration::from_secs(5)));
+    assert!(!conn.closing);
+    conn.exchange(request, false).unwrap();
+    assert!(conn.closing);
+}
+
+#[test]
+fn closed_tcp_connection_is_reopened() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let mut accepted = 0;
+        for body in [&b"one"[..], &b"two"[..]] {
+            let (mut stream, _) = listener.accept().unwrap();
+            accepted += 1;
+            read_request_head(&mut stream);
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n")
+                .unwrap();
+            stream.write_all(body).unwrap();
+        }
+
+        accepted
+    });
+    let mut client = HttpClient::new();
+    let url = format!("http://127.0.0.1:{port}/");
+    let first = client
+        .execute(RequestBuilder::get(&url).protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(first.body, b"one");
+    thread::sleep(std::time::Duration::from_millis(50));
+    let second = client
+        .execute(RequestBuilder::get(&url).protocol(Protocol::HTTP1))
+        .unwrap();
+    assert_eq!(second.body, b"two");
+    assert_eq!(handle.join().unwrap(), 2);
+}
+
+fn read_heads(server: &mut Duplex, count: usize) -> Vec<u8> {
+    let mut received = Vec::new();
+    let mut buffer = [0; 1024];
+    while received.windows(4).filter(|w| w == b"\r\n\r\n").count() < count {
+        let read = server.read(&mut buffer).unwrap();
+        assert_ne!(read, 0, "connection closed before {count} requests");
+        received.extend_from_slice(&buffer[..read]);
+    }
+
+    received
+}
+
+#[test]
+fn pipelined_requests_are_written_back_to_back() {
+    let (client, mut server) = duplex();
+    let handle = thread::spawn(move || {
+        let window = read_heads(&mut server, 3);
+        server
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na\
+                  HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n\
+                  HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nc\r\n0\r\n\r\n",
+            )
+            .unwrap();
+        let last = read_request_head(&mut server);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\nd")
+            .unwrap();
+
+        (window, last)
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![client]),
+    };
+    let mut client = HttpClient::new().connector(connector).pipeline(3);
+    let requests = vec![
+        RequestBuilder::get("http://memory.test/a").protocol(Protocol::HTTP1),
+        RequestBuilder::head("http://memory.test/b").protocol(Protocol::HTTP1),
+        RequestBuilder::get("http://memory.test/c").protocol(Protocol::HTTP1),
+        RequestBuilder::get("http://memory.test/d").protocol(Protocol::HTTP1),
+    ];
+    let bodies: Vec<Vec<u8>> = client
+        .execute_batch(requests)
+        .unwrap()
+        .into_iter()
+        .map(|response| response.unwrap().body)
+        .collect();
+    assert_eq!(
+        bodies,
+        vec![b"a".to_vec(), Vec::new(), b"c".to_vec(), b"d".to_vec()]
+    );
+    let (window, last) = handle.join().unwrap();
+    assert!(window.starts_with(b"GET /a HTTP/1.1\r\n"));
+    assert!(last.starts_with(b"GET /d HTTP/1.1\r\n"));
+}
+
+#[test]
+fn pipeline_falls_back_to_serial_after_close() {
+    let (first, mut closing) = duplex();
+    let (second, mut serial) = duplex();
+    thread::spawn(move || {
+        read_heads(&mut closing, 3);
+        closing
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na")
+            .unwrap();
+    });
+    let handle = thread::spawn(move || {
+        let mut heads = Vec::new();
+        for body in [b"b", b"c"] {
+            heads.push(read_request_head(&mut serial));
+            serial
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n")
+                .unwrap();
+            serial.write_all(body).unwrap();
+        }
+
+        heads
+    });
+    let connector = MemoryConnector {
+        transports: Mutex::new(vec![first, second]),
+    };
+    let mut client = HttpClient::new().connector(connector).pipeline(8);
+    let requests = ["a", "b", "c"]
+        .iter()
+        .map(|path| {
+            RequestBuilder::get(&format!("http://memory.test/{path}")).protocol(Protocol::HTTP1)
+        })
+        .collect();
+    let bodies: Vec<Vec<u8>> = client
+        .execute_batch(requests)
+        .unwrap()
+        .into_iter()
+        .map(|response| response.unwrap().body)
+        .collect();
+    assert_eq!(bodies, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+    let heads = handle.join().unwrap();
+    assert!(heads[0].starts_with(b"GET /b HTTP/1.1\r\n"));
+    assert!(heads[1].starts_with(b"GET /c HTTP/1.1\r\n"));
+    let mismatched = client.execute_batch(vec![
+        RequestBuilder::get("http://memory.test/"),
+        RequestBuilder::get("http://other.test/"),
+    ]);
+    assert!(mismatched.is_err());
+}
@@ -1,59 +1,524 @@
-use crate::http::utf8_utils::{UTF8Utils, QMARK, SLASH};
+use crate::http::utf8_utils::{UTF8Utils, AMPERSAND, COLON, EQUALS, QMARK, SLASH};
+use crate::http::{Error, Result, Success};
 use std::fmt::{Display, Formatter};
+use std::net::Ipv6Addr;
+use std::path::PathBuf;
+use std::str::FromStr;
 
-pub const SCHEME: &[u8] = b"https://";
-pub const AUTHORITY: &[u8] = b"www.";
+mod idna;
+#[cfg(test)]
+mod tests;
 
-#[derive(Clone, Debug)]
+pub const SCHEME_SEPARATOR: &[u8] = b"://";
+pub const UNIX_HOST: &[u8] = b"localhost";
+pub const PERCENT: u8 = b'%';
+pub const HASH: u8 = b'#';
+pub const AT: u8 = b'@';
+const IPV6_OPEN: u8 = b'[';
+const IPV6_CLOSE: u8 = b']';
+const SUB_DELIMS: &[u8] = b"!$&'()*+,;=";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Scheme {
+    Http,
+    #[default]
+    Https,
+    Unix,
+}
+
+impl Scheme {
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::Http | Self::Unix => 80,
+            Self::Https => 443,
+        }
+    }
+
+    pub fn is_secure(&self) -> bool {
+        *self == Self::Https
+    }
+
+    pub fn from_bytes(scheme: &[u8]) -> Option<Self> {
+        match scheme.to_ascii_lowercase().as_slice() {
+            b"http" => Some(Self::Http),
+            b"https" => Some(Self::Https),
+            b"unix" => Some(Self::Unix),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            Self::Http => b"http",
+            Self::Https => b"https",
+            Self::Unix => b"unix",
+        }
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_bytes().as_utf8_lossy())
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Url {
+    pub scheme: Scheme,
+    pub userinfo: Vec<u8>,
     pub host: Vec<u8>,
+    pub port: Option<u16>,
     pub resource: Vec<u8>,
     pub query: Vec<u8>,
+    pub fragment: Vec<u8>,
 }
 
 impl Display for Url {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://", self.scheme)?;
+        if !self.userinfo.is_empty() {
+            write!(f, "{}@", self.userinfo.as_utf8_lossy())?;
+        }
+        let host = match self.scheme {
+            Scheme::Unix => self.host.clone(),
+            _ => self.host_header(),
+        };
         write!(
             f,
-            "{}{}{}",
-            self.host.as_utf8_lossy(),
-            self.resource.as_utf8_lossy(),
-            self.query.as_utf8_lossy()
-        )
+            "{}{}",
+            host.as_utf8_lossy(),
+            self.resource.as_utf8_lossy()
+        )?;
+        if !self.query.is_empty() {
+            write!(f, "?{}", self.query.as_utf8_lossy())?;
+        }
+        if !self.fragment.is_empty() {
+            write!(f, "#{}", self.fragment.as_utf8_lossy())?;
+        }
+
+        Ok(())
     }
 }
 
 impl Url {
+    pub fn parse<T: AsRef<[u8]>>(value: T) -> Result<Self> {
+        let reference = Reference::split(value.as_ref().trim_ascii(), true);
+        let scheme = match reference.scheme {
+            Some(scheme) => {
+                Scheme::from_bytes(scheme).ok_or_else(|| Error::user("unsupported url scheme"))?
+            }
+            None => Scheme::default(),
+        };
+
+        Self::from_reference(scheme, reference)
+    }
+
+    pub(crate) fn reparse(unparsed: &Option<String>) -> Success {
+        match unparsed {
+            Some(url) => Self::parse(url).map(drop),
+            None => Ok(()),
+        }
+    }
+
+    pub fn join(&self, reference: &str) -> Result<Self> {
+        let reference = Reference::split(reference.as_bytes().trim_ascii(), false);
+        if reference.scheme.is_some() || reference.authority.is_some() {
+            let scheme = match reference.scheme {
+                Some(scheme) => Scheme::from_bytes(scheme)
+                    .ok_or_else(|| Error::user("unsupported url scheme"))?,
+                None => self.scheme,
+            };
+            return Self::from_reference(scheme, reference);
+        }
+        let mut url = Self {
+            fragment: reference.fragment.map(encode_fragment).unwrap_or_default(),
+            ..self.clone()
+        };
+        match reference.path.is_empty() {
+            true => {
+                if let Some(query) = reference.query {
+                    url.query = encode_query(query);
+                }
+            }
+            false => {
+                let path = match reference.path.starts_with(&[SLASH]) {
+                    true => reference.path.to_vec(),
+                    false => self.merge(reference.path),
+                };
+                url.resource = normalize_path(&path);
+                url.query = reference.query.map(encode_query).unwrap_or_default();
+            }
+        }
+
+        Ok(url)
+    }
+
+    fn from_reference(scheme: Scheme, reference: Reference) -> Result<Self> {
+        let mut url = Self {
+            scheme,
+            resource: normalize_path(reference.path),
+            query: reference.query.map(encode_query).unwrap_or_default(),
+            fragment: reference.fragment.map(encode_fragment).unwrap_or_default(),
+            ..Default::default()
+        };
+        if let Some(authority) = reference.authority {
+            url.parse_authority(authority)?;
+        }
+
+        Ok(url)
+    }
+
+    fn parse_authority(&mut self, authority: &[u8]) -> Result<()> {
+        let host_port = match authority.iter().rposition(|b| *b == AT) {
+            Some(at) => {
+                self.userinfo = percent_encode(&authority[..at], |b| {
+                    is_unreserved(b) || SUB_DELIMS.contains(&b) || b == COLON
+                });
+                &authority[at + 1..]
+            }
+            None => authority,
+        };
+        let (host, port) = match host_port.first() {
+            Some(&IPV6_OPEN) => {
+                let end = host_port
+                    .iter()
+                    .position(|b| *b == IPV6_CLOSE)
+                    .ok_or_else(|| Error::user("unterminated ipv6 literal"))?;
+                let address = Ipv6Addr::from_str(&String::from_utf8_lossy(&host_port[1..end]))
+                    .map_err(|_| Error::user("invalid ipv6 literal"))?;
+                let port = match &host_port[end + 1..] {
+                    [] => None,
+                    [COLON, port @ ..] => Some(port),
+                    _ => return Err(Error::user("invalid url authority")),
+                };
+                (address.to_string().into_bytes(), port)
+            }
+            _ => {
+                let (host, port) = match host_port.iter().rposition(|b| *b == COLON) {
+                    Some(colon) => (&host_port[..colon], Some(&host_port[colon + 1..])),
+                    None => (host_port, None),
+                };
+                (self.normalize_host(host)?, port)
+            }
+        };
+        self.host = host;
+        self.port = match port {
+            Some(port) if !port.is_empty() => Some(
+                u16::from_str(&String::from_utf8_lossy(port))
+                    .map_err(|_| Error::user("invalid url port"))?,
+            ),
+            _ => None,
+        };
+
+        Ok(())
+    }
+
+    fn normalize_host(&self, host: &[u8]) -> Result<Vec<u8>> {
+        if self.scheme == Scheme::Unix {
+            return Ok(host.to_vec());
+        }
+        let host = String::from_utf8(percent_decode(host))
+            .map_err(|_| Error::user("url host is not valid utf-8"))?;
+        let host = match host.is_ascii() {
+            true => host.to_ascii_lowercase(),
+            false => idna::to_ascii(&host).ok_or_else(|| Error::user("invalid idna hostname"))?,
+        };
+
+        Ok(host.into_bytes())
+    }
+
+    fn merge(&self, path: &[u8]) -> Vec<u8> {
+        let mut merged = match self.resource.iter().rposition(|b| *b == SLASH) {
+            Some(last) => self.resource[..=last].to_vec(),
+            None => vec![SLASH],
+        };
+        merged.extend(path);
+
+        merged
+    }
+
+    pub fn append_query_pair(&mut self, key: &[u8], value: &[u8]) {
+        let mut pair = encode_component(key);
+        pair.push(EQUALS);
+        pair.extend(encode_component(value));
+        self.merge_query(&pair);
+    }
+
+    pub fn merge_query(&mut self, query: &[u8]) {
+        let query = query.strip_prefix(&[QMARK]).unwrap_or(query);
+        if query.is_empty() {
+            return;
+        }
+        if !self.query.is_empty() && !self.query.ends_with(&[AMPERSAND]) {
+            self.query.push(AMPERSAND);
+        }
+        self.query.extend(encode_query(query));
+    }
+
     pub fn authority(&self) -> String {
-        format!("{}:443", self.host.as_utf8_lossy())
+        format!("{}:{}", self.bracketed_host().as_utf8_lossy(), self.port())
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or_else(|| self.scheme.default_port())
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.scheme.is_secure()
+    }
+
+    pub fn host_header(&self) -> Vec<u8> {
+        if self.scheme == Scheme::Unix {
+            return UNIX_HOST.to_vec();
+        }
+        let mut host = self.bracketed_host();
+        if let Some(port) = self.port {
+            if port != self.scheme.default_port() {
+                host.push(COLON);
+                host.extend(port.to_string().as_bytes());
+            }
+        }
+
+        host
+    }
+
+    fn bracketed_host(&self) -> Vec<u8> {
+        match self.host.contains(&COLON) {
+            true => [&[IPV6_OPEN], self.host.as_slice(), &[IPV6_CLOSE]].concat(),
+            false => self.host.clone(),
+        }
+    }
+
+    pub fn credentials(&self) -> Option<(String, String)> {
+        if self.userinfo.is_empty() {
+            return None;
+        }
+        let userinfo = self.userinfo.as_slice();
+        let (username, password) = match userinfo.iter().position(|b| *b == COLON) {
+            Some(colon) => (&userinfo[..colon], &userinfo[colon + 1..]),
+            None => (userinfo, &[][..]),
+        };
+
+        Some((
+            String::from_utf8_lossy(&percent_decode(username)).to_string(),
+            String::from_utf8_lossy(&percent_decode(password)).to_string(),
+        ))
+    }
+
+    pub fn unix_path(&self) -> Option<PathBuf> {
+        match self.scheme {
+            Scheme::Unix => Some(PathBuf::from(
+                String::from_utf8_lossy(&percent_decode(&self.host)).to_string(),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn origin(&self) -> Self {
+        Self {
+            scheme: self.scheme,
+            userinfo: Vec::new(),
+            host: self.host.clone(),
+            port: self.port,
+            resource: Vec::new(),
+            query: Vec::new(),
+            fragment: Vec::new(),
+        }
     }
 }
 
 impl<T: AsRef<[u8]>> From<T> for Url {
     fn from(value: T) -> Self {
-        let mut value = value.as_ref();
-        if value.starts_with(SCHEME) {
-            value = &value[7..];
+        Self::parse(value.as_ref()).unwrap_or_else(|_| Self {
+            host: value.as_ref().to_vec(),
+            ..Default::default()
+        })
+    }
+}
+
+struct Reference<'a> {
+    scheme: Option<&'a [u8]>,
+    authority: Option<&'a [u8]>,
+    path: &'a [u8],
+    query: Option<&'a [u8]>,
+    fragment: Option<&'a [u8]>,
+}
+
+impl<'a> Reference<'a> {
+    fn split(value: &'a [u8], implicit_authority: bool) -> Self {
+        let (rest, fragment) = split_once(value, HASH);
+        let (mut rest, query) = split_once(rest, QMARK);
+        let scheme = rest
+            .windows(SCHEME_SEPARATOR.len())
+            .position(|w| w == SCHEME_SEPARATOR)
+            .filter(|end| is_scheme(&rest[..*end]))
+            .map(|end| {
+                let scheme = &rest[..end];
+                rest = &rest[end + 1..];
+                scheme
+            });
+        let has_authority = rest.starts_with(&[SLASH, SLASH]);
+        if has_authority {
+            rest = &rest[2..];
         }
-        if value.starts_with(AUTHORITY) {
-            value = &value[3..];
+        let (authority, path) = match has_authority || (scheme.is_none() && implicit_authority) {
+            true => {
+                let end = rest.iter().position(|b| *b == SLASH).unwrap_or(rest.len());
+                (Some(&rest[..end]), &rest[end..])
+            }
+            false => (None, rest),
+        };
+
+        Self {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
         }
-        let mut host = Vec::with_capacity(2048);
-        let mut resource = Vec::new();
-        let mut query = Vec::new();
-        let mut value = value.iter().peekable();
-        while let Some(byte) = value.next_if(|b| **b != SLASH) {
-            host.push(*byte);
+    }
+}
+
+fn split_once(value: &[u8], delimiter: u8) -> (&[u8], Option<&[u8]>) {
+    match value.iter().position(|b| *b == delimiter) {
+        Some(i) => (&value[..i], Some(&value[i + 1..])),
+        None => (value, None),
+    }
+}
+
+fn is_scheme(scheme: &[u8]) -> bool {
+    scheme.first().is_some_and(u8::is_ascii_alphabetic)
+        && scheme
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(b))
+}
+
+pub(crate) fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~".contains(&byte)
+}
+
+fn is_pchar(byte: u8) -> bool {
+    is_unreserved(byte) || SUB_DELIMS.contains(&byte) || byte == COLON || byte == AT
+}
+
+fn encode_query(query: &[u8]) -> Vec<u8> {
+    percent_encode(query, |b| is_pchar(b) || b == SLASH || b == QMARK)
+}
+
+fn encode_fragment(fragment: &[u8]) -> Vec<u8> {
+    percent_encode(fragment, |b| is_pchar(b) || b == SLASH || b == QMARK)
+}
+
+fn normalize_path(path: &[u8]) -> Vec<u8> {
+    remove_dot_segments(&percent_encode(path, |b| is_pchar(b) || b == SLASH))
+}
+
+pub(crate) fn percent_encode<F: Fn(u8) -> bool>(value: &[u8], allowed: F) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(value.len());
+    let mut i = 0;
+    while i < value.len() {
+        let escaped = match value[i] {
+            PERCENT => value.get(i + 1..i + 3).and_then(decode_hex),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) if is_unreserved(byte) => {
+                encoded.push(byte);
+                i += 3;
+            }
+            Some(byte) => {
+                push_escaped(&mut encoded, byte);
+                i += 3;
+            }
+            None => {
+                match allowed(value[i]) && value[i] != PERCENT {
+                    true => encoded.push(value[i]),
+                    false => push_escaped(&mut encoded, value[i]),
+                }
+                i += 1;
+            }
+        }
+    }
+
+    encoded
+}
+
+pub(crate) fn encode_component(value: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(value.len());
+    for byte in value {
+        match is_unreserved(*byte) {
+            true => encoded.push(*byte),
+            false => push_escaped(&mut encoded, *byte),
         }
-        while let Some(byte) = value.next_if(|b| **b != QMARK) {
-            resource.push(*byte);
+    }
+
+    encoded
+}
+
+fn push_escaped(encoded: &mut Vec<u8>, byte: u8) {
+    encoded.push(PERCENT);
+    encoded.extend(format!("{byte:02X}").as_bytes());
+}
+
+fn decode_hex(hex: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+pub(crate) fn percent_decode(value: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut i = 0;
+    while i < value.len() {
+        let byte = match value[i] {
+            PERCENT => value.get(i + 1..i + 3).and_then(decode_hex),
+            _ => None,
+        };
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(value[i]);
+                i += 1;
+            }
         }
-        query.extend(value);
+    }
 
-        Self {
-            host,
-            resource,
-            query,
+    decoded
+}
+
+fn remove_dot_segments(path: &[u8]) -> Vec<u8> {
+    let mut input = path;
+    let mut output: Vec<u8> = Vec::with_capacity(path.len());
+    while !input.is_empty() {
+        if let Some(rest) = input
+            .strip_prefix(b"../")
+            .or_else(|| input.strip_prefix(b"./"))
+        {
+            input = rest;
+        } else if input.starts_with(b"/./") {
+            input = &input[2..];
+        } else if input == b"/." {
+            input = b"/";
+        } else if input.starts_with(b"/../") || input == b"/.." {
+            input = match input.len() {
+                3 => b"/",
+                _ => &input[3..],
+            };
+            let last = output.iter().rposition(|b| *b == SLASH).unwrap_or(0);
+            output.truncate(last);
+        } else if input == b"." || input == b".." {
+            input = &[];
+        } else {
+            let start = usize::from(input[0] == SLASH);
+            let end = input[start..]
+                .iter()
+                .position(|b| *b == SLASH)
+                .map_or(input.len(), |i| i + start);
+            output.extend(&input[..end]);
+            input = &input[end..];
         }
     }
+
+    output
 }
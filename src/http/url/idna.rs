@@ -0,0 +1,93 @@
+const ACE_PREFIX: &str = "xn--";
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 0x80;
+
+pub fn to_ascii(host: &str) -> Option<String> {
+    let labels = host
+        .split('.')
+        .map(|label| {
+            let label: String = label.chars().flat_map(char::to_lowercase).collect();
+            match label.is_ascii() {
+                true => Some(label),
+                false => punycode(&label).map(|encoded| format!("{ACE_PREFIX}{encoded}")),
+            }
+        })
+        .collect::<Option<Vec<String>>>()?;
+
+    Some(labels.join("."))
+}
+
+fn punycode(label: &str) -> Option<String> {
+    let input: Vec<u32> = label.chars().map(|c| c as u32).collect();
+    let mut output: String = label.chars().filter(char::is_ascii).collect();
+    let basic = output.len() as u32;
+    let mut handled = basic;
+    if basic > 0 {
+        output.push('-');
+    }
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    while (handled as usize) < input.len() {
+        let m = input.iter().copied().filter(|c| *c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+        for c in input.iter().copied() {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = match k {
+                        k if k <= bias => T_MIN,
+                        k if k >= bias + T_MAX => T_MAX,
+                        k => k - bias,
+                    };
+                    if q < t {
+                        break;
+                    }
+                    output.push(digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta = delta.checked_add(1)?;
+        n = n.checked_add(1)?;
+    }
+
+    Some(output)
+}
+
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = match first {
+        true => delta / DAMP,
+        false => delta / 2,
+    };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn digit(d: u32) -> char {
+    match d {
+        0..=25 => (b'a' + d as u8) as char,
+        _ => (b'0' + (d - 26) as u8) as char,
+    }
+}
@@ -15,6 +15,7 @@ pub const COLSP: &[u8; 2] = &[COLON, SP];
 pub const SLASH: u8 = 0x2f;
 pub const QMARK: u8 = 0x3f;
 pub const EQUALS: u8 = 0x3d;
+pub const AMPERSAND: u8 = 0x26;
 
 pub const HEX_DIGITS: &[u8; 22] = &[
     0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46,
@@ -33,7 +34,6 @@ pub trait UTF8Utils {
     fn is_hex(&self) -> bool;
     fn as_lower(&self) -> Vec<u8>;
     fn to_lower(self) -> Vec<u8>;
-    fn strip_null(self) -> Vec<u8>;
     fn trim_crlf(self) -> Vec<u8>;
     fn trim_chars(self, chars: &[u8]) -> Vec<u8>;
     fn into_utf8_parser(self) -> UTF8Parser;
@@ -79,12 +79,6 @@ impl<T: Deref<Target = [u8]>> UTF8Utils for T {
             .collect()
     }
 
-    fn strip_null(self) -> Vec<u8> {
-        self.into_iter()
-            .filter(|b| **b != NULL)
-            .map(|b| *b)
-            .collect()
-    }
     fn trim_crlf(self) -> Vec<u8> {
         let mut vec = self.to_vec();
         while vec.ends_with(CRLF) && !vec.is_empty() {
@@ -125,7 +119,7 @@ impl<T: UTF8Utils> From<T> for UTF8Parser {
 
 impl UTF8Parser {
     pub fn new(buffer: &[u8]) -> Self {
-        let iter = buffer.strip_null().into_iter().peekable();
+        let iter = Vec::from(buffer).into_iter().peekable();
 
         Self { iter }
     }
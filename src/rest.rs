@@ -15,4 +15,8 @@ pub type Results<T> = std::result::Result<Vec<T>, Error>;
 pub type ClientConfig = client::ConfigBuilder;
 pub use crate::http::error::ErrorKind as HttpErrorKind;
 pub use crate::http::Error as HttpError;
+pub use crate::http::{
+    BodyCompression, Compression, Connector, ContentEncoding, Method, PinMismatch, Proxy,
+    ResponseBody, SpkiPin, StreamingResponse, TimeoutKind, Timeouts, TlsOptions, Transport,
+};
 pub use client::Client;
@@ -8,9 +8,12 @@ use interpreter::Interpreter;
 use std::thread;
 use std::time::Duration;
 
+use crate::http::url::Url;
+use crate::http::utf8_utils::SLASH;
 #[cfg(feature = "http2")]
 pub use crate::http::Protocol::HTTP2;
 pub use crate::http::Protocol::{self, HTTP1};
+use crate::http::{Method, Result as HttpResult};
 use crate::rest::client::config::Config;
 pub use crate::rest::client::config::ConfigBuilder;
 use crate::rest::{
@@ -39,7 +42,7 @@ pub struct Client {
 impl From<ConfigBuilder> for Client {
     fn from(config: ConfigBuilder) -> Self {
         Self {
-            inner: HttpClient::new(),
+            inner: HttpClient::with_options(config.connection.clone()),
             config: config.into(),
             access: None,
             #[cfg(feature = "interpreter")]
@@ -97,60 +100,67 @@ impl Client {
     }
 
     pub fn get(&mut self, url: &str) -> Request {
-        let url = self.with_resource(url);
-        Request::new(
-            InnerRequest::get(&url).protocol(self.config.default_protocol),
-            self,
-        )
+        self.request(Method::GET, url)
     }
 
     pub fn post(&mut self, url: &str) -> Request {
-        let url = self.with_resource(url);
-        Request::new(
-            InnerRequest::post(&url).protocol(self.config.default_protocol),
-            self,
-        )
+        self.request(Method::POST, url)
     }
 
     pub fn put(&mut self, url: &str) -> Request {
-        let url = self.with_resource(url);
-        Request::new(
-            InnerRequest::put(&url).protocol(self.config.default_protocol),
-            self,
-        )
+        self.request(Method::PUT, url)
     }
 
     pub fn patch(&mut self, url: &str) -> Request {
-        let url = self.with_resource(url);
-        Request::new(
-            InnerRequest::patch(&url).protocol(self.config.default_protocol),
-            self,
-        )
+        self.request(Method::PATCH, url)
     }
 
     pub fn delete(&mut self, url: &str) -> Request {
-        let url = self.with_resource(url);
-        Request::new(
-            InnerRequest::delete(&url).protocol(self.config.default_protocol),
-            self,
-        )
+        self.request(Method::DELETE, url)
     }
 
     pub fn connect(&mut self, url: &str) -> Request {
-        let url = self.with_resource(url);
-        Request::new(
-            InnerRequest::connect(&url).protocol(self.config.default_protocol),
-            self,
-        )
+        self.request(Method::CONNECT, url)
     }
 
-    fn with_resource(&mut self, url: &str) -> String {
-        match url.starts_with("/") {
-            true => format!("{}{url}", self.config.base_url),
-            false => format!("{}/{url}", self.config.base_url),
+    pub fn head(&mut self, url: &str) -> Request<'_> {
+        self.request(Method::HEAD, url)
+    }
+
+    pub fn options(&mut self, url: &str) -> Request<'_> {
+        self.request(Method::OPTIONS, url)
+    }
+
+    pub fn trace(&mut self, url: &str) -> Request<'_> {
+        self.request(Method::TRACE, url)
+    }
+
+    pub fn request(&mut self, method: Method, url: &str) -> Request<'_> {
+        let protocol = self.config.default_protocol;
+        match self.with_resource(url) {
+            Ok(url) => Request::new(InnerRequest::new(method, url).protocol(protocol), self),
+            Err(e) => Request::invalid(
+                InnerRequest::new(method, Url::default()).protocol(protocol),
+                e,
+                self,
+            ),
         }
     }
 
+    fn with_resource(&self, url: &str) -> HttpResult<Url> {
+        Url::reparse(&self.config.unparsed_base_url)?;
+        let mut base = self.config.base_url.clone();
+        if !base.resource.ends_with(&[SLASH]) {
+            base.resource.push(SLASH);
+        }
+        let reference = match url.starts_with("//") {
+            true => url,
+            false => url.strip_prefix('/').unwrap_or(url),
+        };
+
+        base.join(reference)
+    }
+
     #[cfg(feature = "http2")]
     pub fn set_http1(&mut self) -> Result<Success> {
         self.inner
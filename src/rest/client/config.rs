@@ -1,11 +1,15 @@
 use crate::http::url::Url;
-use crate::http::Protocol;
+use crate::http::{
+    BodyCompression, ConnOptions, Connector, Protocol, Proxy, Response as InnerResponse, SpkiPin,
+    Timeouts, TlsOptions,
+};
 use crate::rest::client::auth::Grant;
 #[cfg(feature = "http2")]
 use crate::rest::client::HTTP1;
 use crate::rest::client::{Auth, AuthBuilder};
 use crate::rest::{Client, Error, Result};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 pub struct Config {
     pub auth: Option<Auth>,
@@ -13,51 +17,48 @@ pub struct Config {
     pub base_url: Url,
     pub required_headers: HashMap<String, String>,
     pub default_protocol: Protocol,
+    pub(crate) unparsed_base_url: Option<String>,
 }
 
 impl From<ConfigBuilder> for Config {
     fn from(builder: ConfigBuilder) -> Self {
+        let (base_url, unparsed_base_url) = match Url::parse(&builder.base_url) {
+            Ok(url) => (url, None),
+            Err(_) => (Url::default(), Some(builder.base_url)),
+        };
         Self {
             auth: match builder.auth {
                 Some(auth) => Some(auth.finalize()),
                 None => None,
             },
             backoff_proc: builder.backoff_proc,
-            base_url: builder.base_url,
+            base_url,
             required_headers: builder.required_headers,
             default_protocol: builder.default_protocol,
+            unparsed_base_url,
         }
     }
 }
 
+#[derive(Default)]
 pub struct ConfigBuilder {
     pub(crate) auth: Option<AuthBuilder>,
     pub(crate) backoff_proc: BackOffProcedure,
-    pub(crate) base_url: Url,
+    pub(crate) base_url: String,
     pub(crate) required_headers: HashMap<String, String>,
     pub(crate) default_protocol: Protocol,
-}
-
-impl Default for ConfigBuilder {
-    fn default() -> Self {
-        Self {
-            backoff_proc: BackOffProcedure::default(),
-            base_url: "".into(),
-            required_headers: Default::default(),
-            auth: None,
-            default_protocol: Default::default(),
-        }
-    }
+    pub(crate) connection: ConnOptions,
 }
 
 impl From<&str> for ConfigBuilder {
     fn from(base_url: &str) -> Self {
         Self {
             backoff_proc: Default::default(),
-            base_url: base_url.into(),
+            base_url: base_url.to_string(),
             required_headers: Default::default(),
             auth: None,
             default_protocol: Default::default(),
+            connection: Default::default(),
         }
     }
 }
@@ -71,6 +72,7 @@ impl ConfigBuilder {
     }
     pub fn preconnect(self) -> Result<Client> {
         let mut client = self.build();
+        Url::reparse(&client.config.unparsed_base_url).map_err(Error::from)?;
         client
             .inner
             .connect_proto(&client.config.base_url, client.config.default_protocol)
@@ -86,8 +88,62 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: self.auth,
             default_protocol: HTTP1,
+            connection: self.connection,
         }
     }
+    pub fn compress_requests(mut self, compression: BodyCompression) -> Self {
+        self.connection = self.connection.compress_requests(compression);
+
+        self
+    }
+    pub fn on_informational<F: Fn(&InnerResponse) + Send + Sync + 'static>(
+        mut self,
+        handler: F,
+    ) -> Self {
+        self.connection = self.connection.on_informational(handler);
+
+        self
+    }
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        self.connection = self.connection.tls(tls);
+
+        self
+    }
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.connection = self.connection.timeouts(timeouts);
+
+        self
+    }
+    pub fn connector<C: Connector + 'static>(mut self, connector: C) -> Self {
+        self.connection = self.connection.connector(connector);
+
+        self
+    }
+    pub fn unix_socket<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.connection = self.connection.unix_socket(path);
+
+        self
+    }
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.connection = self.connection.proxy(proxy);
+
+        self
+    }
+    pub fn proxy_from_env(mut self) -> Self {
+        self.connection = self.connection.proxy_from_env();
+
+        self
+    }
+    pub fn pin_sha256(mut self, host: &str, pin: SpkiPin) -> Self {
+        self.connection.tls = self.connection.tls.pin_sha256(host, pin);
+
+        self
+    }
+    pub fn pin_sha256_base64(mut self, host: &str, pin: &str) -> Result<Self> {
+        self.connection.tls = self.connection.tls.pin_sha256_base64(host, pin)?;
+
+        Ok(self)
+    }
     pub fn backoff(self, proc: BackOffProcedure) -> Self {
         Self {
             backoff_proc: proc,
@@ -95,15 +151,17 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: self.auth,
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
     pub fn base_url(self, url: &str) -> Self {
         Self {
             backoff_proc: self.backoff_proc,
-            base_url: url.into(),
+            base_url: url.to_string(),
             required_headers: self.required_headers,
             auth: self.auth,
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
     pub fn required_headers(self, headers: &[(&str, &str)]) -> Self {
@@ -116,6 +174,7 @@ impl ConfigBuilder {
                 .collect(),
             auth: self.auth,
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
     pub fn required_header(mut self, header: (&str, &str)) -> Self {
@@ -128,6 +187,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: self.auth,
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
     pub fn auth(self, auth: AuthBuilder) -> Self {
@@ -137,6 +197,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -148,6 +209,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -159,6 +221,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -170,6 +233,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -181,6 +245,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -192,6 +257,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -203,6 +269,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -214,6 +281,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -225,6 +293,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -236,6 +305,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -247,6 +317,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -258,6 +329,7 @@ impl ConfigBuilder {
             required_headers: self.required_headers,
             auth: Some(auth),
             default_protocol: self.default_protocol,
+            connection: self.connection,
         }
     }
 
@@ -40,6 +40,7 @@ impl From<HttpError> for Error {
             | HttpErrorKind::Protocol => Kind::Protocol,
             HttpErrorKind::Server => Kind::Server,
             HttpErrorKind::Connection(_) => Kind::Connection,
+            HttpErrorKind::Timeout(..) => Kind::Timeout,
             #[cfg(feature = "http2")]
             HttpErrorKind::Http2Protocol(_) => Kind::Protocol,
         };
@@ -53,6 +54,7 @@ pub enum Kind {
     Parse,
     Status(u16),
     Connection,
+    Timeout,
     Server,
     Client,
     Protocol,
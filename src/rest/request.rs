@@ -1,9 +1,10 @@
 use crate::http::request::headers::AUTHORIZATION;
 pub use crate::http::request::headers::*;
-pub use crate::http::request::RequestBuilder as InnerRequest;
+pub use crate::http::request::{ArrayFormat, RequestBuilder as InnerRequest};
 #[cfg(feature = "multipart")]
 use crate::http::utf8_utils::UTF8Utils;
 pub use crate::http::Response as InnerResponse;
+use crate::http::{BodyCompression, Error as HttpError, StreamingResponse};
 use crate::rest::client::auth::OAUTH;
 use crate::rest::client::{
     auth::AccessTokenResponse, auth::BASIC, auth::BEARER, AuthMethod, AuthPlacement, Success,
@@ -11,7 +12,7 @@ use crate::rest::client::{
 use crate::rest::error::SomeError;
 #[cfg(feature = "multipart")]
 use crate::rest::multipart::MultipartForm;
-use crate::rest::request::values::{ALL, JSON};
+use crate::rest::request::values::{ALL, JSON, OCTET_STREAM};
 use crate::{
     rest::{response::Response, Error, ErrorKind, Result},
     RestClient,
@@ -19,14 +20,21 @@ use crate::{
 use base64::encode;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
 
 pub struct Request<'a> {
     pub(crate) inner: InnerRequest,
     client_ref: &'a mut RestClient,
+    invalid: Option<HttpError>,
 }
 
 impl<'a> Request<'a> {
     pub fn send(mut self) -> Result<Response> {
+        if let Some(e) = self.invalid.take() {
+            return Err(Error::from(e));
+        }
         self.set_auth()?;
         self.set_required_headers();
         match self.client_ref.will_retry() {
@@ -35,6 +43,18 @@ impl<'a> Request<'a> {
         }
     }
 
+    pub fn send_streaming(mut self) -> Result<StreamingResponse> {
+        if let Some(e) = self.invalid.take() {
+            return Err(Error::from(e));
+        }
+        self.set_auth()?;
+        self.set_required_headers();
+        self.client_ref
+            .inner
+            .send_streaming(self.inner)
+            .map_err(Error::from)
+    }
+
     pub fn expect_json<T>(self) -> Result<T>
     where
         T: DeserializeOwned,
@@ -52,23 +72,78 @@ impl<'a> Request<'a> {
     }
 
     pub(crate) fn new(inner: InnerRequest, client_ref: &'a mut RestClient) -> Self {
-        Self { inner, client_ref }
+        Self {
+            inner,
+            client_ref,
+            invalid: None,
+        }
+    }
+
+    pub(crate) fn invalid(
+        inner: InnerRequest,
+        error: HttpError,
+        client_ref: &'a mut RestClient,
+    ) -> Self {
+        Self {
+            inner,
+            client_ref,
+            invalid: Some(error),
+        }
     }
 
     pub fn body<T: Serialize>(self, body: &T) -> Self {
         let body = serde_json::to_vec(body).unwrap();
         Self {
             inner: self.inner.body(&body).header((CONTENT_TYPE, JSON)),
-            client_ref: self.client_ref,
+            ..self
+        }
+    }
+
+    pub fn body_reader<R: Read + Send + 'static>(self, reader: R, length: Option<u64>) -> Self {
+        let inner = self.inner.body_reader(reader, length);
+        let inner = match inner.headers.contains(CONTENT_TYPE) {
+            true => inner,
+            false => inner.header((CONTENT_TYPE, OCTET_STREAM)),
+        };
+
+        Self { inner, ..self }
+    }
+
+    pub fn body_file<P: AsRef<Path>>(self, path: P) -> Result<Self> {
+        let file = File::open(path).map_err(|e| Error::from(HttpError::from(e)))?;
+        let length = file
+            .metadata()
+            .map_err(|e| Error::from(HttpError::from(e)))?
+            .len();
+
+        Ok(self.body_reader(file, Some(length)))
+    }
+
+    pub fn compress(self, compression: BodyCompression) -> Self {
+        Self {
+            inner: self.inner.compress(compression),
+            ..self
         }
     }
 
     pub fn query<T: serde::Serialize + Sized>(mut self, query: T) -> Self {
         let query = serde_urlencoded::to_string(query).unwrap();
-        self.inner.set_query(query.into_bytes());
+        self.inner.merge_query(query.as_bytes());
         Self {
             inner: self.inner,
-            client_ref: self.client_ref,
+            ..self
+        }
+    }
+
+    pub fn query_array<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        self,
+        key: K,
+        values: &[V],
+        format: ArrayFormat,
+    ) -> Self {
+        Self {
+            inner: self.inner.query_array(key, values, format),
+            ..self
         }
     }
 
@@ -93,21 +168,21 @@ impl<'a> Request<'a> {
 
         Self {
             inner: self.inner.body(body.to_bytes().as_slice()),
-            client_ref: self.client_ref,
+            ..self
         }
     }
 
     pub fn header(self, key: &[u8], value: &[u8]) -> Self {
         Self {
             inner: self.inner.header((key, value)),
-            client_ref: self.client_ref,
+            ..self
         }
     }
 
     pub fn headers(self, headers: Vec<(&[u8], &[u8])>) -> Self {
         Self {
             inner: self.inner.headers(headers),
-            client_ref: self.client_ref,
+            ..self
         }
     }
 
@@ -3,11 +3,11 @@ pub use crate::http::Response as InnerResponse;
 use crate::rest::error::SomeError;
 use crate::{
     http::utf8_utils::UTF8Utils,
+    http::{Compression, HeaderMap},
     rest::{Error, ErrorKind, HttpError, Result},
 };
 use serde::de::DeserializeOwned;
 use serde_json;
-use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -70,9 +70,13 @@ impl Response {
     pub fn status(&self) -> &u16 {
         &self.inner.status_code
     }
-    pub fn headers(&self) -> &HashMap<String, String> {
+    pub fn headers(&self) -> &HeaderMap {
         &self.inner.headers
     }
+
+    pub fn compression(&self) -> Option<&Compression> {
+        self.inner.compression.as_ref()
+    }
     pub fn assert(&self) {
         assert!(self.is_ok())
     }
@@ -222,3 +222,155 @@ fn http1_test() {
     }
     print_results(results);
 }
+
+#[test]
+fn query_merges_with_url_and_auth() {
+    use crate::http::test_utils::serve_once;
+    use crate::rest::client::auth::Credentials;
+    use crate::rest::client::{Auth, HTTP1};
+    let (port, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+    let mut config = Client::config()
+        .base_url(&format!("http://127.0.0.1:{port}"))
+        .auth(Auth::new().credentials(Credentials::custom(vec![("api_key", "k&y")]).query()));
+    config.default_protocol = HTTP1;
+    let mut client = config.build();
+    client
+        .get("search?lang=en")
+        .query([("q", "a b")])
+        .send()
+        .unwrap();
+    let request = server.join().unwrap();
+    assert!(request.starts_with(b"GET /search?lang=en&q=a+b&api_key=k%26y HTTP/1.1\r\n"));
+}
+
+#[test]
+fn streaming_response_body() {
+    use crate::http::test_utils::serve_once;
+    use crate::rest::client::HTTP1;
+    use std::io::Read;
+    let (port, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world");
+    let mut config = Client::config().base_url(&format!("http://127.0.0.1:{port}"));
+    config.default_protocol = HTTP1;
+    let mut client = config.build();
+    let mut response = client
+        .get("download")
+        .bearer_auth("token")
+        .send_streaming()
+        .unwrap();
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.headers.content_length(), Some(11));
+    let mut body = String::new();
+    response.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "hello world");
+    let request = server.join().unwrap();
+    assert!(request.starts_with(b"GET /download HTTP/1.1\r\n"));
+    assert!(request.windows(21).any(|w| w == b"Authorization: Bearer"));
+}
+
+#[test]
+fn body_file_uploads_with_length() {
+    use crate::http::test_utils::serve_once;
+    use crate::rest::client::HTTP1;
+    let path = std::env::temp_dir().join(format!("envoy-upload-{}", std::process::id()));
+    std::fs::write(&path, b"file contents").unwrap();
+    let (port, server) = serve_once(b"HTTP/1.1 204 No Content\r\n\r\n");
+    let mut config = Client::config().base_url(&format!("http://127.0.0.1:{port}"));
+    config.default_protocol = HTTP1;
+    let mut client = config.build();
+    let response = client
+        .post("upload")
+        .header(b"Content-Type", b"text/csv")
+        .body_file(&path)
+        .unwrap()
+        .send()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(*response.status(), 204);
+    let request = String::from_utf8(server.join().unwrap()).unwrap();
+    assert!(request.contains("Content-Length: 13\r\n"));
+    assert!(request.contains("Content-Type: text/csv\r\n"));
+    assert!(!request.contains("application/octet-stream"));
+    assert!(client.get("missing").body_file(&path).is_err());
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn client_compresses_json_bodies() {
+    use crate::http::test_utils::serve_once;
+    use crate::rest::client::HTTP1;
+    let (port, server) = serve_once(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n");
+    let mut config = Client::config()
+        .base_url(&format!("http://127.0.0.1:{port}"))
+        .compress_requests(BodyCompression::new(ContentEncoding::Gzip).threshold(16));
+    config.default_protocol = HTTP1;
+    let mut client = config.build();
+    let events: Vec<u32> = (0..500).collect();
+    client.post("events").body(&events).send().unwrap();
+    let request = server.join().unwrap();
+    let head_end = request.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+    assert!(head.contains("Content-Encoding: gzip\r\n"));
+    let length: usize = head
+        .split("\r\n")
+        .find_map(|line| line.strip_prefix("Content-Length: "))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(length < serde_json::to_vec(&events).unwrap().len());
+}
+
+#[test]
+fn head_and_extension_methods() {
+    use crate::http::test_utils::serve_once;
+    use crate::http::Method;
+    use crate::rest::client::HTTP1;
+    let (port, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\n\r\n");
+    let mut config = Client::config().base_url(&format!("http://127.0.0.1:{port}"));
+    config.default_protocol = HTTP1;
+    let mut client = config.build();
+    let response = client.head("files/report.pdf").send().unwrap();
+    assert_eq!(*response.status(), 200);
+    assert!(server
+        .join()
+        .unwrap()
+        .starts_with(b"HEAD /files/report.pdf HTTP/1.1\r\n"));
+
+    let (port, server) = serve_once(b"HTTP/1.1 207 Multi-Status\r\nContent-Length: 0\r\n\r\n");
+    let mut config = Client::config().base_url(&format!("http://127.0.0.1:{port}"));
+    config.default_protocol = HTTP1;
+    let mut client = config.build();
+    let method = "PROPFIND".parse::<Method>().unwrap();
+    assert_eq!(method, Method::Extension("PROPFIND".to_string()));
+    client.request(method, "dav/").send().unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .starts_with(b"PROPFIND /dav/ HTTP/1.1\r\n"));
+
+    assert_eq!("OPTIONS".parse::<Method>().unwrap(), Method::OPTIONS);
+    let (port, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\n\r\n");
+    let mut config = Client::config().base_url(&format!("http://127.0.0.1:{port}"));
+    config.default_protocol = HTTP1;
+    let mut client = config.build();
+    client
+        .request(Method::Extension("HEAD".to_string()), "files/report.pdf")
+        .send()
+        .unwrap();
+    assert!(server.join().unwrap().starts_with(b"HEAD /files/"));
+    assert!("BAD METHOD".parse::<Method>().is_err());
+    let invalid = Method::Extension("GET /\r\n".to_string());
+    assert!(client.request(invalid, "dav/").send().is_err());
+}
+
+#[test]
+fn invalid_urls_fail_on_send() {
+    let mut client = Client::new("http://localhost:99999");
+    let error = client.get("users").send().unwrap_err();
+    assert!(error.to_string().contains("invalid url port"));
+    assert!(Client::config()
+        .base_url("http://localhost:99999")
+        .preconnect()
+        .is_err());
+    let mut client = Client::new("http://localhost:8080");
+    assert!(client.get("//[::1/users").send().is_err());
+}